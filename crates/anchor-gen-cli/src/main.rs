@@ -0,0 +1,169 @@
+//! Writes an Anchor CPI crate generated from a JSON IDL to disk.
+//!
+//! The generated crate is what [`generate_cpi_crate!`](https://docs.rs/anchor-gen) expands to,
+//! formatted so that it can be vendored, reviewed and diffed.
+//!
+//! # Usage
+//!
+//! ```text
+//! anchor-gen examples/govern-cpi/idl.json --out govern-cpi --zero-copy Governor
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fs,
+    path::PathBuf,
+};
+
+use anchor_idl::{Generator, SerdeOpts, GEN_VERSION};
+use clap::Parser;
+use quote::quote;
+
+/// Version of Anchor the generated code is built and tested against, which generated crates
+/// depend on unless `--anchor-version` is passed.
+const ANCHOR_VERSION: &str = "0.24.2";
+
+/// Generates an Anchor CPI crate from a JSON IDL.
+#[derive(Parser)]
+#[command(name = "anchor-gen", version)]
+struct Args {
+    /// Path to the JSON IDL.
+    idl: PathBuf,
+    /// Directory to write the crate to.
+    #[arg(short, long)]
+    out: PathBuf,
+    /// Name of the crate. Defaults to the program name followed by `-cpi`.
+    #[arg(long)]
+    name: Option<String>,
+    /// Program id. Defaults to the address in the IDL.
+    #[arg(long)]
+    program_id: Option<String>,
+    /// Structs to make zero copy.
+    #[arg(long, value_delimiter = ',')]
+    zero_copy: Vec<String>,
+    /// Structs to make `repr(packed)`.
+    #[arg(long, value_delimiter = ',')]
+    packed: Vec<String>,
+    /// Typedefs to replace with existing types, as `Name=path::to::Type`.
+    #[arg(long, value_parser = parse_type_mapping)]
+    type_map: Vec<(String, String)>,
+    /// Choose zero copy and `repr(packed)` structs from the IDL, in addition to the listed ones.
+    /// Accounts made zero copy are left out of `AccountType`.
+    #[arg(long)]
+    auto_layout: bool,
+    /// Derive serde's `Serialize` and `Deserialize`, enabling the `serde` feature of `anchor-gen`.
+    #[arg(long)]
+    serde: bool,
+    /// Serialize 64 and 128-bit integers as strings. Implies `--serde`.
+    #[arg(long)]
+    serde_ints_as_strings: bool,
+    /// Only write `src/lib.rs`, leaving any existing Cargo.toml untouched.
+    #[arg(long)]
+    no_manifest: bool,
+    /// Version requirement of `anchor-lang` in the generated Cargo.toml.
+    #[arg(long, default_value = ANCHOR_VERSION)]
+    anchor_version: String,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let idl_contents = fs::read_to_string(&args.idl)
+        .map_err(|e| format!("failed to read {}: {}", args.idl.display(), e))?;
+    let idl = anchor_idl::parse_idl(&idl_contents)
+        .map_err(|e| format!("failed to parse {}: {}", args.idl.display(), e))?;
+    let type_map: BTreeMap<String, String> = args.type_map.iter().cloned().collect();
+    anchor_idl::validate_idl(&idl)
+        .and_then(|_| anchor_idl::validate_type_map(&idl, &type_map))
+        .map_err(|e| format!("invalid IDL {}: {}", args.idl.display(), e))?;
+
+    let program_id = args
+        .program_id
+        .clone()
+        .or_else(|| idl.address.clone())
+        .or_else(|| {
+            idl.metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get("address"))
+                .and_then(|address| address.as_str())
+                .map(str::to_string)
+        })
+        .ok_or("the IDL has no address, pass one with --program-id")?;
+    let crate_name = args
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{}-cpi", idl.name.replace('_', "-")));
+
+    let zero_copy: HashSet<String> = args.zero_copy.iter().cloned().collect();
+    let packed: HashSet<String> = args.packed.iter().cloned().collect();
+    let mut gen = Generator::new(idl, &zero_copy, &packed).with_type_map(&type_map);
+    if args.auto_layout {
+        gen = gen.with_auto_layout();
+    }
+    let serde = args.serde || args.serde_ints_as_strings;
+    if serde {
+        gen = gen.with_serde(SerdeOpts {
+            ints_as_strings: args.serde_ints_as_strings,
+        });
+    }
+
+    let interface = gen.generate_cpi_interface();
+    let type_enums = gen.generate_type_enums();
+    let file: syn::File = syn::parse2(quote! {
+        #interface
+        #type_enums
+
+        declare_id!(#program_id);
+    })?;
+    let lib = format!(
+        "// Generated by anchor-gen v{} from {}. Do not edit.\n\n{}",
+        GEN_VERSION.unwrap_or("unknown"),
+        args.idl.display(),
+        prettyplease::unparse(&file)
+    );
+
+    let src_dir = args.out.join("src");
+    fs::create_dir_all(&src_dir)?;
+    fs::write(src_dir.join("lib.rs"), lib)?;
+    if !args.no_manifest {
+        fs::write(
+            args.out.join("Cargo.toml"),
+            manifest(&crate_name, &args.anchor_version, serde),
+        )?;
+    }
+    Ok(())
+}
+
+fn parse_type_mapping(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, path)| (name.to_string(), path.to_string()))
+        .ok_or_else(|| format!("expected `Name=path::to::Type`, got `{}`", s))
+}
+
+/// Renders the Cargo.toml of the generated crate, with the features Anchor's `#[program]` expects.
+fn manifest(crate_name: &str, anchor_version: &str, serde: bool) -> String {
+    let features = if serde { r#", features = ["serde"]"# } else { "" };
+    format!(
+        r#"[package]
+name = "{}"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+default = ["cpi"]
+no-entrypoint = []
+no-idl = []
+no-log-ix-name = []
+cpi = ["no-entrypoint"]
+
+[dependencies]
+anchor-gen = {{ version = "{}"{} }}
+anchor-lang = "{}"
+"#,
+        crate_name,
+        GEN_VERSION.unwrap_or("*"),
+        features,
+        anchor_version
+    )
+}
@@ -21,9 +21,12 @@ pub use anchor_generate_cpi_interface::generate_cpi_interface;
 pub use anchor_idl::get_type_name;
 pub use anchor_idl::derive_account_type;
 pub use anchor_idl::derive_instruction_type;
+pub use anchor_idl::derive_event_type;
 pub use anchor_idl::Decode;
+pub use anchor_idl::DecodeError;
 pub use anchor_idl::NameToDiscrim;
 pub use anchor_idl::DiscrimToName;
+pub use anchor_idl::split_account_keys;
 
 pub mod prelude {
   pub use anchor_generate_cpi_crate::generate_cpi_crate;
@@ -31,7 +34,17 @@ pub mod prelude {
   pub use anchor_idl::get_type_name;
   pub use anchor_idl::derive_account_type;
   pub use anchor_idl::derive_instruction_type;
+  pub use anchor_idl::derive_event_type;
   pub use anchor_idl::Decode;
+  pub use anchor_idl::DecodeError;
   pub use anchor_idl::NameToDiscrim;
   pub use anchor_idl::DiscrimToName;
 }
+
+/// Crates used by the serde derives of the generated code.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub mod __serde {
+  pub use serde;
+  pub use serde_with;
+}
@@ -14,8 +14,8 @@
 //!
 //! More examples can be found in the [examples/](https://github.com/saber-hq/anchor-gen/tree/master/examples) directory.
 
-use quote::quote;
 use anchor_idl::GeneratorOptions;
+use darling::util::SpannedValue;
 use syn::{parse_macro_input, LitStr};
 
 /// Generates an Anchor CPI crate from a JSON file.
@@ -43,53 +43,15 @@ use syn::{parse_macro_input, LitStr};
 pub fn generate_cpi_crate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let id_literal = parse_macro_input!(input as LitStr);
     let opts = GeneratorOptions {
-        idl_path: id_literal.value(),
+        idl_path: SpannedValue::new(id_literal.value(), id_literal.span()),
         ..Default::default()
     };
 
-    let gen = opts.to_generator();
-    let mut ts: proc_macro2::TokenStream = gen.generate_cpi_interface();
-
-    let acct_variants = gen.account_types().into_iter().map(|ident| {
-        let variant_name = ident.clone();
-        quote! { #variant_name(#ident) }
-    });
-    let account_ts = quote! {
-        anchor_gen::derive_account_type!(
-            pub enum AccountType {
-                #(#acct_variants,)*
-            }
-        );
+    let gen = match opts.to_generator() {
+        Ok(gen) => gen,
+        Err(e) => return e.to_compile_error().into(),
     };
-    ts.extend(account_ts);
-
-    
-    let ix_variants = gen.instruction_types().into_iter().map(|ident| {
-        let variant_name = ident.clone();
-    
-        // Construct the path prefix
-        let path_prefix: syn::Path = syn::parse_str("instruction").unwrap();
-    
-        // Create a new PathSegment with the input Ident
-        let mut segments = path_prefix.segments.clone();
-        segments.push(syn::PathSegment::from(ident));
-    
-        // Combine the path prefix and the Ident
-        let full_path = syn::Path {
-            leading_colon: path_prefix.leading_colon,
-            segments,
-        };
-    
-        quote! { #variant_name(#full_path) }
-    });
-    let ix_ts = quote! {
-        anchor_gen::derive_instruction_type!(
-            pub enum InstructionType {
-                #(#ix_variants,)*
-            }
-        );
-    };
-    ts.extend(ix_ts);
-    
+    let mut ts: proc_macro2::TokenStream = gen.generate_cpi_interface();
+    ts.extend(gen.generate_type_enums());
     ts.into()
-}
\ No newline at end of file
+}
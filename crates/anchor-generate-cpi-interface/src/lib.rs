@@ -49,5 +49,8 @@ pub fn generate_cpi_interface(input: proc_macro::TokenStream) -> TokenStream {
             return TokenStream::from(e.write_errors());
         }
     };
-    parsed.to_generator().generate_cpi_interface().into()
+    match parsed.to_generator() {
+        Ok(gen) => gen.generate_cpi_interface().into(),
+        Err(e) => e.to_compile_error().into(),
+    }
 }
@@ -1,7 +1,16 @@
-pub use anchor_syn::idl::*;
-use heck::{ToPascalCase, ToSnakeCase};
+use crate::{generate_docs, type_ident, unique_field_idents, GenIdent, IdlAccountItem};
+use heck::ToPascalCase;
 use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
+use quote::quote;
+
+/// Generates the identifiers of the fields of an accounts struct, renaming the ones which would
+/// collide.
+pub fn account_field_idents(accounts: &[IdlAccountItem]) -> Vec<GenIdent> {
+    unique_field_idents(accounts.iter().map(|account| match account {
+        IdlAccountItem::IdlAccount(info) => info.name.as_str(),
+        IdlAccountItem::IdlAccounts(inner) => inner.name.as_str(),
+    }))
+}
 
 /// Generates a list of [IdlAccountItem]s as a [TokenStream].
 pub fn generate_account_fields(
@@ -11,9 +20,11 @@ pub fn generate_account_fields(
     let mut all_structs: Vec<TokenStream> = vec![];
     let all_fields = accounts
         .iter()
-        .map(|account| match account {
-            anchor_syn::idl::IdlAccountItem::IdlAccount(info) => {
-                let acc_name = format_ident!("{}", info.name.to_snake_case());
+        .zip(account_field_idents(accounts))
+        .map(|(account, acc_name)| match account {
+            IdlAccountItem::IdlAccount(info) => {
+                let docs = generate_docs(info.docs.as_deref());
+                let alias = acc_name.doc_alias();
                 let annotation = if info.is_mut {
                     quote! { #[account(mut)] }
                 } else {
@@ -25,14 +36,16 @@ pub fn generate_account_fields(
                     quote! { AccountInfo<'info> }
                 };
                 quote! {
+                   #docs
+                   #alias
                    #annotation
                    pub #acc_name: #ty
                 }
             }
-            anchor_syn::idl::IdlAccountItem::IdlAccounts(inner) => {
-                let field_name = format_ident!("{}{}", name, inner.name.to_snake_case());
+            IdlAccountItem::IdlAccounts(inner) => {
                 let sub_name = format!("{}{}", name, inner.name.to_pascal_case());
-                let sub_ident = format_ident!("{}", &sub_name);
+                let sub_ident = type_ident(&sub_name);
+                let alias = acc_name.doc_alias();
                 let (sub_structs, sub_fields) = generate_account_fields(&sub_name, &inner.accounts);
                 all_structs.push(sub_structs);
                 all_structs.push(quote! {
@@ -42,7 +55,8 @@ pub fn generate_account_fields(
                     }
                 });
                 quote! {
-                    pub #field_name: #sub_ident<'info>
+                    #alias
+                    pub #acc_name: #sub_ident<'info>
                 }
             }
         })
@@ -56,3 +70,26 @@ pub fn generate_account_fields(
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renamed_fields_keep_the_idl_name_as_alias() {
+        let accounts: Vec<IdlAccountItem> = serde_json::from_str(
+            r#"[{"name": "self", "isMut": true, "isSigner": false},
+                {"name": "crate", "accounts": [{"name": "owner", "isMut": false, "isSigner": true}]}]"#,
+        )
+        .unwrap();
+        let (_, fields) = generate_account_fields("Swap", &accounts);
+        let fields = fields.to_string();
+        assert!(fields.contains(
+            &quote! { #[doc(alias = "self")] #[account(mut)] pub self_: AccountInfo<'info> }
+                .to_string()
+        ));
+        assert!(fields.contains(
+            &quote! { #[doc(alias = "crate")] pub crate_: SwapCrate<'info> }.to_string()
+        ));
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Generator, SerdeOpts};
+
+/// Generates CPI crates from build scripts, as an alternative to the `generate_cpi_crate!` macro.
+///
+/// Each IDL is written to `OUT_DIR` as `<program name>.rs`, which is meant to be included at
+/// the root of a crate, since Anchor's `#[program]` refers to items through `crate::`.
+/// The crate needs `anchor-lang` and `anchor-gen` as dependencies, just like one using the macro.
+///
+/// ```ignore
+/// // build.rs
+/// fn main() -> std::io::Result<()> {
+///     anchor_idl::Builder::new()
+///         .idl("idl.json")
+///         .zero_copy(["User"])
+///         .out_file("drift.rs")
+///         .generate()
+/// }
+///
+/// // src/lib.rs
+/// include!(concat!(env!("OUT_DIR"), "/drift.rs"));
+/// declare_id!("dRiftyHA39MWEi3m9aunc5MzRF1JYuBsbn6VPcn33UH");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    idls: Vec<PathBuf>,
+    zero_copy: HashSet<String>,
+    packed: HashSet<String>,
+    type_map: BTreeMap<String, String>,
+    auto_layout: bool,
+    serde: Option<SerdeOpts>,
+    out_dir: Option<PathBuf>,
+    out_file: Option<String>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an IDL to generate code for, relative to the crate's Cargo.toml.
+    pub fn idl(mut self, path: impl AsRef<Path>) -> Self {
+        self.idls.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Structs to make zero copy.
+    pub fn zero_copy<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.zero_copy.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Structs to make `repr(packed)`.
+    pub fn packed<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.packed.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Replaces the typedef `name` with the existing type at `path`, such as `my_crate::OrderParams`.
+    pub fn type_map(mut self, name: impl Into<String>, path: impl Into<String>) -> Self {
+        self.type_map.insert(name.into(), path.into());
+        self
+    }
+
+    /// Chooses zero copy and `repr(packed)` structs from the IDL, in addition to the listed ones.
+    /// Accounts made zero copy are left out of `AccountType`.
+    pub fn auto_layout(mut self) -> Self {
+        self.auto_layout = true;
+        self
+    }
+
+    /// Derives serde's `Serialize` and `Deserialize`. The crate needs the `serde` feature of `anchor-gen`.
+    pub fn serde(mut self, opts: SerdeOpts) -> Self {
+        self.serde = Some(opts);
+        self
+    }
+
+    /// Directory to write to. Defaults to `OUT_DIR`.
+    pub fn out_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.out_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Name of the file to write, instead of `<program name>.rs`. Only valid with a single IDL.
+    pub fn out_file(mut self, name: impl Into<String>) -> Self {
+        self.out_file = Some(name.into());
+        self
+    }
+
+    /// Generates the code for every IDL and tells Cargo to rerun the build script when one changes.
+    pub fn generate(self) -> io::Result<()> {
+        if self.out_file.is_some() && self.idls.len() > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "out_file can only be set when generating a single IDL",
+            ));
+        }
+        let out_dir = match &self.out_dir {
+            Some(out_dir) => out_dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?,
+        };
+
+        for path in &self.idls {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let contents = fs::read_to_string(path)?;
+            let idl = crate::parse_idl(&contents).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to parse {}: {}", path.display(), e),
+                )
+            })?;
+            crate::validate_idl(&idl)
+                .and_then(|_| crate::validate_type_map(&idl, &self.type_map))
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid IDL {}: {}", path.display(), e),
+                    )
+                })?;
+            let file_name = self
+                .out_file
+                .clone()
+                .unwrap_or_else(|| format!("{}.rs", idl.name));
+
+            let mut gen =
+                Generator::new(idl, &self.zero_copy, &self.packed).with_type_map(&self.type_map);
+            if self.auto_layout {
+                gen = gen.with_auto_layout();
+            }
+            if let Some(serde) = self.serde {
+                gen = gen.with_serde(serde);
+            }
+            let mut tokens = gen.generate_cpi_interface();
+            tokens.extend(gen.generate_type_enums());
+            let file: syn::File = syn::parse2(tokens)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            fs::write(out_dir.join(file_name), prettyplease::unparse(&file))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDL: &str = r#"{"version": "0.1.0", "name": "counter",
+        "instructions": [{"name": "increment", "args": [{"name": "by", "type": "u64"}],
+            "accounts": [{"name": "counter", "isMut": true, "isSigner": false}]}],
+        "accounts": [{"name": "Counter", "type": {"kind": "struct", "fields": [
+            {"name": "count", "type": "u64"}]}}]}"#;
+
+    /// Creates an empty directory for a test to write to.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("anchor-idl-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn writes_the_out_file() {
+        let dir = test_dir("out-file");
+        let idl = dir.join("idl.json");
+        fs::write(&idl, IDL).unwrap();
+
+        Builder::new()
+            .idl(&idl)
+            .out_dir(&dir)
+            .out_file("generated.rs")
+            .generate()
+            .unwrap();
+        let generated = fs::read_to_string(dir.join("generated.rs")).unwrap();
+        assert!(generated.contains("pub struct Counter"));
+        assert!(generated.contains("pub fn increment("));
+        assert!(!dir.join("counter.rs").exists());
+
+        Builder::new().idl(&idl).out_dir(&dir).generate().unwrap();
+        assert!(dir.join("counter.rs").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn out_file_needs_a_single_idl() {
+        let err = Builder::new()
+            .idl("a.json")
+            .idl("b.json")
+            .out_file("generated.rs")
+            .generate()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
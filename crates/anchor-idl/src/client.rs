@@ -0,0 +1,329 @@
+use heck::ToPascalCase;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{
+    account_field_idents, fn_ident, generate_docs, generate_leading_docs, generate_serde_derives,
+    generate_serde_field_attr, idl_instruction_discriminator, ix_arg_idents, ix_struct_ident,
+    type_ident, GenIdent, IdlAccountItem, IdlInstruction, IdlType, Pdas, SerdeOpts,
+};
+
+/// Generates the client accounts struct for a list of [IdlAccountItem]s, along with
+/// the structs of any nested account groups.
+///
+/// The accounts at the `derived` paths, as listed by [Pdas::derived_accounts], are optional
+/// since the instruction builder can derive them.
+///
+/// The structs can also be read back from the keys of an instruction, which hold the accounts in
+/// the order of the IDL with the nested groups flattened, as in [crate::generate_account_fields].
+pub fn generate_client_accounts_struct(
+    name: &str,
+    accounts: &[IdlAccountItem],
+    derived: &[String],
+    serde: Option<SerdeOpts>,
+) -> TokenStream {
+    let struct_name = type_ident(name);
+    let mut all_structs: Vec<TokenStream> = vec![];
+    let mut fields: Vec<TokenStream> = vec![];
+    let mut metas: Vec<TokenStream> = vec![];
+    let mut from_keys: Vec<TokenStream> = vec![];
+    // Position of the next account in the keys: a count of single accounts plus the lengths of
+    // the groups before it.
+    let mut offset = AccountOffset::default();
+    for (account, acc_name) in accounts.iter().zip(account_field_idents(accounts)) {
+        let alias = acc_name.doc_alias();
+        let index = offset.to_token_stream();
+        match account {
+            IdlAccountItem::IdlAccount(info) => {
+                let is_signer = info.is_signer;
+                let docs = generate_docs(info.docs.as_deref());
+                let (derived_doc, ty, idl_ty, key, from_key) = if derived.contains(&info.name) {
+                    let msg = format!("`{}` is derived by the instruction builder", info.name);
+                    (
+                        quote! {
+                            /// Derived from its seeds by the instruction builder when `None`.
+                        },
+                        quote! { Option<Pubkey> },
+                        IdlType::Option(Box::new(IdlType::PublicKey)),
+                        quote! { self.#acc_name.expect(#msg) },
+                        quote! { Some(keys[#index]) },
+                    )
+                } else {
+                    (
+                        quote! {},
+                        quote! { Pubkey },
+                        IdlType::PublicKey,
+                        quote! { self.#acc_name },
+                        quote! { keys[#index] },
+                    )
+                };
+                let serde_attr = generate_serde_field_attr(&idl_ty, serde);
+                fields.push(quote! {
+                    #docs
+                    #derived_doc
+                    #alias
+                    #serde_attr
+                    pub #acc_name: #ty
+                });
+                from_keys.push(quote! {
+                    #acc_name: #from_key
+                });
+                offset.accounts += 1;
+                metas.push(if info.is_mut {
+                    quote! {
+                        account_metas.push(AccountMeta::new(#key, is_signer.unwrap_or(#is_signer)));
+                    }
+                } else {
+                    quote! {
+                        account_metas.push(AccountMeta::new_readonly(#key, is_signer.unwrap_or(#is_signer)));
+                    }
+                });
+            }
+            IdlAccountItem::IdlAccounts(inner) => {
+                let sub_name = format!("{}{}", name, inner.name.to_pascal_case());
+                let sub_ident = type_ident(&sub_name);
+                let prefix = format!("{}.", inner.name);
+                let sub_derived: Vec<String> = derived
+                    .iter()
+                    .filter_map(|path| path.strip_prefix(&prefix).map(str::to_string))
+                    .collect();
+                all_structs.push(generate_client_accounts_struct(
+                    &sub_name,
+                    &inner.accounts,
+                    &sub_derived,
+                    serde,
+                ));
+                fields.push(quote! {
+                    #alias
+                    pub #acc_name: #sub_ident
+                });
+                metas.push(quote! {
+                    account_metas.extend(self.#acc_name.to_account_metas(is_signer));
+                });
+                from_keys.push(quote! {
+                    #acc_name: #sub_ident::from_account_keys(&keys[#index..])
+                });
+                offset.groups.push(sub_ident);
+            }
+        }
+    }
+    let accounts_len = offset.to_token_stream();
+    let keys_name = if accounts.is_empty() {
+        quote! { _keys }
+    } else {
+        quote! { keys }
+    };
+    let derive_serde = generate_serde_derives(serde);
+
+    quote! {
+        #(#all_structs)*
+
+        #[derive(Clone, Copy, Debug)]
+        #derive_serde
+        pub struct #struct_name {
+            #(#fields),*
+        }
+
+        impl #struct_name {
+            /// Number of accounts, including those of the nested groups.
+            pub const ACCOUNTS_LEN: usize = #accounts_len;
+
+            /// Reads the accounts from the keys of an instruction, in the order of the IDL.
+            ///
+            /// # Panics
+            ///
+            /// Panics if there are fewer than [Self::ACCOUNTS_LEN] keys. Extra keys are ignored.
+            pub fn from_account_keys(#keys_name: &[Pubkey]) -> Self {
+                Self {
+                    #(#from_keys),*
+                }
+            }
+        }
+
+        impl anchor_lang::ToAccountMetas for #struct_name {
+            fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+                let mut account_metas = vec![];
+                #(#metas)*
+                account_metas
+            }
+        }
+    }
+}
+
+/// Position of an account in the keys of an instruction, or number of accounts of a struct.
+#[derive(Default)]
+struct AccountOffset {
+    /// Number of single accounts.
+    accounts: usize,
+    /// Client structs of the nested groups, whose `ACCOUNTS_LEN` are added to the count.
+    groups: Vec<GenIdent>,
+}
+
+impl AccountOffset {
+    fn to_token_stream(&self) -> TokenStream {
+        let groups = self.groups.iter().map(|group| quote! { #group::ACCOUNTS_LEN });
+        if self.groups.is_empty() || self.accounts > 0 {
+            let accounts = self.accounts;
+            quote! { #accounts #(+ #groups)* }
+        } else {
+            quote! { #(#groups)+* }
+        }
+    }
+}
+
+/// Generates the argument list of an instruction's builder.
+pub fn generate_ix_args(ix: &IdlInstruction) -> Vec<TokenStream> {
+    ix.args
+        .iter()
+        .zip(ix_arg_idents(ix))
+        .map(|(arg, name)| {
+            let type_name = crate::ty_to_rust_type(&arg.ty);
+            let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
+            quote! {
+                #name: #stream
+            }
+        })
+        .collect()
+}
+
+/// Generates an `# Arguments` section documenting the args of an instruction's builder,
+/// or nothing if none of them have docs.
+pub fn generate_ix_args_docs(ix: &IdlInstruction) -> TokenStream {
+    if ix.args.iter().all(|arg| arg.docs.is_none()) {
+        return quote! {};
+    }
+    let lines = ix
+        .args
+        .iter()
+        .zip(ix_arg_idents(ix))
+        .filter_map(|(arg, name)| {
+            let docs = arg.docs.as_ref()?.join(" ");
+            Some(format!(" * `{}` - {}", name.unraw(), docs))
+        });
+    quote! {
+        #[doc = ""]
+        #[doc = " # Arguments"]
+        #[doc = ""]
+        #(#[doc = #lines])*
+    }
+}
+
+/// Generates the statements building `instruction_data` from the discriminator and
+/// the Borsh-serialized arguments.
+pub fn generate_ix_data(ix: &IdlInstruction) -> TokenStream {
+    let discriminator = idl_instruction_discriminator(ix);
+    let serialize_args = ix_arg_idents(ix).into_iter().map(|name| {
+        quote! {
+            AnchorSerialize::serialize(&#name, &mut instruction_data).unwrap();
+        }
+    });
+    quote! {
+        let mut instruction_data: Vec<u8> = vec![#(#discriminator),*];
+        #(#serialize_args)*
+    }
+}
+
+/// Generates a single client instruction builder, which derives the PDAs left out of its
+/// accounts.
+pub fn generate_ix_builder(ix: &IdlInstruction, pdas: &Pdas) -> TokenStream {
+    let ix_name = fn_ident(&ix.name);
+    let alias = ix_name.doc_alias();
+    let accounts_name = ix_struct_ident(&ix.name);
+    let args = generate_ix_args(ix);
+    let resolution = pdas.generate_resolution(ix);
+    let ix_data = generate_ix_data(ix);
+
+    let docs = generate_leading_docs(ix.docs.as_deref());
+    let args_docs = generate_ix_args_docs(ix);
+    let doc = format!(" Builds a `{}` instruction.", ix.name);
+    quote! {
+        #docs
+        #[doc = #doc]
+        #args_docs
+        #alias
+        pub fn #ix_name(
+            accounts: &accounts::#accounts_name,
+            #(#args),*
+        ) -> Instruction {
+            #resolution
+            #ix_data
+            Instruction {
+                program_id: ID,
+                accounts: anchor_lang::ToAccountMetas::to_account_metas(accounts, None),
+                data: instruction_data,
+            }
+        }
+    }
+}
+
+/// Generates the client accounts structs and instruction builders.
+pub fn generate_client(ixs: &[IdlInstruction], pdas: &Pdas, serde: Option<SerdeOpts>) -> TokenStream {
+    let accounts = ixs.iter().map(|ix| {
+        generate_client_accounts_struct(
+            &ix_struct_ident(&ix.name).to_string(),
+            &ix.accounts,
+            &pdas.derived_accounts(ix),
+            serde,
+        )
+    });
+    let builders = ixs.iter().map(|ix| generate_ix_builder(ix, pdas));
+    quote! {
+        use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+
+        pub mod accounts {
+            //! Accounts used to build instructions, keyed by [Pubkey].
+            use super::*;
+            #(#accounts)*
+        }
+
+        #(#builders)*
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_are_read_back_from_keys_in_idl_order() {
+        let accounts: Vec<IdlAccountItem> = serde_json::from_str(
+            r#"[{"name": "pool", "isMut": true, "isSigner": false},
+                {"name": "vaults", "accounts": [
+                    {"name": "vaultA", "isMut": true, "isSigner": false},
+                    {"name": "vaultB", "isMut": true, "isSigner": false}]},
+                {"name": "owner", "isMut": false, "isSigner": true},
+                {"name": "log", "isMut": true, "isSigner": false}]"#,
+        )
+        .unwrap();
+        let generated =
+            generate_client_accounts_struct("Swap", &accounts, &["log".to_string()], None)
+                .to_string();
+
+        let vaults_len = quote! { pub const ACCOUNTS_LEN: usize = 2usize; };
+        assert!(generated.contains(&vaults_len.to_string()));
+        let len = quote! { pub const ACCOUNTS_LEN: usize = 3usize + SwapVaults::ACCOUNTS_LEN; };
+        assert!(generated.contains(&len.to_string()));
+
+        let from_keys = quote! {
+            pub fn from_account_keys(keys: &[Pubkey]) -> Self {
+                Self {
+                    pool: keys[0usize],
+                    vaults: SwapVaults::from_account_keys(&keys[1usize..]),
+                    owner: keys[1usize + SwapVaults::ACCOUNTS_LEN],
+                    log: Some(keys[2usize + SwapVaults::ACCOUNTS_LEN])
+                }
+            }
+        };
+        assert!(generated.contains(&from_keys.to_string()));
+    }
+
+    #[test]
+    fn instructions_without_accounts_read_no_keys() {
+        let generated = generate_client_accounts_struct("Noop", &[], &[], None).to_string();
+        let expected = quote! {
+            pub const ACCOUNTS_LEN: usize = 0usize;
+        };
+        assert!(generated.contains(&expected.to_string()));
+        assert!(generated.contains(&quote! { from_account_keys(_keys: &[Pubkey]) }.to_string()));
+    }
+}
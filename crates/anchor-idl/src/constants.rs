@@ -0,0 +1,192 @@
+use std::{collections::HashSet, str::FromStr};
+
+use anchor_lang::prelude::Pubkey;
+use proc_macro2::{Literal, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+
+use crate::{generate_docs, type_ident, IdlConst, IdlType};
+
+/// Names which may appear in the value of a numeric constant besides other constants,
+/// such as in `(10 * SECONDS_PER_DAY) as i64`.
+const PRIMITIVE_NAMES: &[&str] = &[
+    "as", "true", "false", "u8", "i8", "u16", "i16", "u32", "i32", "u64", "i64", "u128", "i128",
+    "usize", "isize", "f32", "f64",
+];
+
+/// Generates a single constant, or nothing if its value can't be represented, such as when
+/// it refers to items of the program which are not in the IDL.
+pub fn generate_constant(constant: &IdlConst, names: &HashSet<&str>) -> Option<TokenStream> {
+    let (ty, value) = parse_const_value(&constant.ty, &constant.value, names)?;
+    let name = type_ident(&constant.name);
+    let alias = name.doc_alias();
+    let docs = generate_docs(constant.docs.as_deref());
+    // Public keys are written out as bytes, so their address is kept in the docs.
+    let address = match constant.ty {
+        IdlType::PublicKey => {
+            let address = format!(" `{}`", parse_pubkey(&constant.value)?);
+            quote! { #[doc = #address] }
+        }
+        _ => quote! {},
+    };
+    Some(quote! {
+        #docs
+        #address
+        #alias
+        pub const #name: #ty = #value;
+    })
+}
+
+/// Generates the constants of the IDL.
+///
+/// Constants may only refer to constants which are generated themselves, so dropping one can
+/// drop those which refer to it, and so on until no more are dropped.
+pub fn generate_constants(constants: &[IdlConst]) -> TokenStream {
+    let mut names: HashSet<&str> = constants.iter().map(|c| c.name.as_str()).collect();
+    let constants = loop {
+        let generated: Vec<(&str, TokenStream)> = constants
+            .iter()
+            .filter_map(|constant| {
+                generate_constant(constant, &names).map(|tokens| (constant.name.as_str(), tokens))
+            })
+            .collect();
+        let generated_names: HashSet<&str> = generated.iter().map(|(name, _)| *name).collect();
+        if generated_names == names {
+            break generated.into_iter().map(|(_, tokens)| tokens);
+        }
+        names = generated_names;
+    };
+    quote! {
+        #(#constants)*
+    }
+}
+
+/// Parses the stringified value of a constant, as written by Anchor, into its Rust type
+/// and value.
+fn parse_const_value(
+    ty: &IdlType,
+    value: &str,
+    names: &HashSet<&str>,
+) -> Option<(TokenStream, TokenStream)> {
+    match ty {
+        // `pubkey!` refers to `::solana_program`, which the generated crate may not depend on.
+        IdlType::PublicKey => {
+            let bytes = parse_pubkey(value)?.to_bytes();
+            Some((
+                quote! { Pubkey },
+                quote! { Pubkey::new_from_array([#(#bytes),*]) },
+            ))
+        }
+        IdlType::String => {
+            let value = match syn::parse_str::<syn::LitStr>(value) {
+                Ok(lit) => lit.value(),
+                Err(_) => value.to_string(),
+            };
+            Some((quote! { &str }, quote! { #value }))
+        }
+        IdlType::Bytes => {
+            let bytes = parse_bytes(value)?;
+            Some((quote! { &[u8] }, quote! { &[#(#bytes),*] }))
+        }
+        IdlType::Array(inner, len) if **inner == IdlType::U8 => {
+            let bytes = parse_bytes(value)?;
+            if bytes.len() != *len {
+                return None;
+            }
+            let len = Literal::usize_unsuffixed(*len);
+            Some((quote! { [u8; #len] }, quote! { [#(#bytes),*] }))
+        }
+        IdlType::Bool
+        | IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::F32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::F64
+        | IdlType::U128
+        | IdlType::I128 => {
+            let expr: TokenStream = syn::parse_str::<syn::Expr>(value).ok()?.into_token_stream();
+            if !refers_only_to(expr.clone(), names) {
+                return None;
+            }
+            let ty: TokenStream = crate::ty_to_rust_type(ty).parse().unwrap();
+            Some((ty, expr))
+        }
+        _ => None,
+    }
+}
+
+/// Parses a public key written in base58, optionally quoted or wrapped in `pubkey!`.
+fn parse_pubkey(value: &str) -> Option<Pubkey> {
+    let key = value
+        .trim()
+        .trim_start_matches("pubkey!")
+        .trim_start_matches("pubkey !")
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '"'));
+    Pubkey::from_str(key).ok()
+}
+
+/// Parses bytes written either as a byte string, such as `b"seed"`, or as an array of integers.
+fn parse_bytes(value: &str) -> Option<Vec<Literal>> {
+    if let Ok(lit) = syn::parse_str::<syn::LitByteStr>(value) {
+        return Some(lit.value().into_iter().map(Literal::u8_unsuffixed).collect());
+    }
+    let array = syn::parse_str::<syn::ExprArray>(value).ok()?;
+    array
+        .elems
+        .iter()
+        .map(|elem| match elem {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) => int.base10_parse::<u8>().ok().map(Literal::u8_unsuffixed),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns true if every identifier in `tokens` is another constant or a primitive.
+fn refers_only_to(tokens: TokenStream, names: &HashSet<&str>) -> bool {
+    tokens.into_iter().all(|token| match token {
+        TokenTree::Ident(ident) => {
+            let ident = ident.to_string();
+            names.contains(ident.as_str()) || PRIMITIVE_NAMES.contains(&ident.as_str())
+        }
+        TokenTree::Group(group) => refers_only_to(group.stream(), names),
+        _ => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant(name: &str, ty: IdlType, value: &str) -> IdlConst {
+        IdlConst {
+            name: name.to_string(),
+            docs: None,
+            ty,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn drops_constants_referring_to_dropped_constants() {
+        let constants = [
+            constant("BASE", IdlType::U64, "crate::state::FOO"),
+            constant("DOUBLE", IdlType::U64, "BASE * 2"),
+            constant("QUADRUPLE", IdlType::U64, "DOUBLE * 2"),
+            constant("ONE", IdlType::U64, "1"),
+            constant("TWO", IdlType::U64, "ONE * 2"),
+        ];
+        let generated = generate_constants(&constants).to_string();
+        assert!(!generated.contains("BASE"));
+        assert!(!generated.contains("DOUBLE"));
+        assert!(!generated.contains("QUADRUPLE"));
+        assert!(generated.contains("pub const ONE : u64 = 1 ;"));
+        assert!(generated.contains("pub const TWO : u64 = ONE * 2 ;"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    account_field_idents, fn_ident, generate_docs, generate_leading_docs, ix_struct_ident, type_ident, IdlAccountItem, IdlInstruction,
+};
+use heck::ToPascalCase;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{generate_ix_args, generate_ix_args_docs, generate_ix_data};
+
+/// Generates the CPI accounts struct for a list of [IdlAccountItem]s, along with
+/// the structs of any nested account groups.
+///
+/// Unlike the structs in `ix_accounts`, every account is an [AccountInfo] so that
+/// PDAs may sign through `invoke_signed`.
+pub fn generate_cpi_accounts_struct(name: &str, accounts: &[IdlAccountItem]) -> TokenStream {
+    let struct_name = type_ident(name);
+    let mut all_structs: Vec<TokenStream> = vec![];
+    let mut fields: Vec<TokenStream> = vec![];
+    let mut metas: Vec<TokenStream> = vec![];
+    let mut infos: Vec<TokenStream> = vec![];
+    for (account, acc_name) in accounts.iter().zip(account_field_idents(accounts)) {
+        let alias = acc_name.doc_alias();
+        match account {
+            IdlAccountItem::IdlAccount(info) => {
+                let is_signer = info.is_signer;
+                let docs = generate_docs(info.docs.as_deref());
+                fields.push(quote! {
+                    #docs
+                    #alias
+                    pub #acc_name: AccountInfo<'info>
+                });
+                metas.push(if info.is_mut {
+                    quote! {
+                        account_metas.push(AccountMeta::new(*self.#acc_name.key, is_signer.unwrap_or(#is_signer)));
+                    }
+                } else {
+                    quote! {
+                        account_metas.push(AccountMeta::new_readonly(*self.#acc_name.key, is_signer.unwrap_or(#is_signer)));
+                    }
+                });
+                infos.push(quote! {
+                    account_infos.push(self.#acc_name.clone());
+                });
+            }
+            IdlAccountItem::IdlAccounts(inner) => {
+                let sub_name = format!("{}{}", name, inner.name.to_pascal_case());
+                let sub_ident = type_ident(&sub_name);
+                all_structs.push(generate_cpi_accounts_struct(&sub_name, &inner.accounts));
+                fields.push(quote! {
+                    #alias
+                    pub #acc_name: #sub_ident<'info>
+                });
+                metas.push(quote! {
+                    account_metas.extend(self.#acc_name.to_account_metas(is_signer));
+                });
+                infos.push(quote! {
+                    account_infos.extend(self.#acc_name.to_account_infos());
+                });
+            }
+        }
+    }
+
+    quote! {
+        #(#all_structs)*
+
+        #[derive(Clone)]
+        pub struct #struct_name<'info> {
+            #(#fields),*
+        }
+
+        impl<'info> anchor_lang::ToAccountMetas for #struct_name<'info> {
+            fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+                let mut account_metas = vec![];
+                #(#metas)*
+                account_metas
+            }
+        }
+
+        impl<'info> anchor_lang::ToAccountInfos<'info> for #struct_name<'info> {
+            fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+                let mut account_infos = vec![];
+                #(#infos)*
+                account_infos
+            }
+        }
+    }
+}
+
+/// Generates a single CPI helper.
+pub fn generate_cpi_helper(ix: &IdlInstruction) -> TokenStream {
+    let ix_name = fn_ident(&ix.name);
+    let alias = ix_name.doc_alias();
+    let accounts_name = ix_struct_ident(&ix.name);
+    let args = generate_ix_args(ix);
+    let ix_data = generate_ix_data(ix);
+
+    let ret = if cfg!(feature = "compat-program-result") {
+        quote! { ProgramResult }
+    } else {
+        quote! { Result<()> }
+    };
+
+    let docs = generate_leading_docs(ix.docs.as_deref());
+    let args_docs = generate_ix_args_docs(ix);
+    let doc = format!(" Invokes the `{}` instruction.", ix.name);
+    quote! {
+        #docs
+        #[doc = #doc]
+        #args_docs
+        #alias
+        pub fn #ix_name<'a, 'b, 'c, 'info>(
+            ctx: CpiContext<'a, 'b, 'c, 'info, accounts::#accounts_name<'info>>,
+            #(#args),*
+        ) -> #ret {
+            #ix_data
+            let ix = Instruction {
+                program_id: ID,
+                accounts: ctx.to_account_metas(None),
+                data: instruction_data,
+            };
+            anchor_lang::solana_program::program::invoke_signed(
+                &ix,
+                &ctx.to_account_infos(),
+                ctx.signer_seeds,
+            )
+            .map_err(Into::into)
+        }
+    }
+}
+
+/// Generates the CPI accounts structs and helpers.
+pub fn generate_cpi_helpers(ixs: &[IdlInstruction]) -> TokenStream {
+    let accounts = ixs
+        .iter()
+        .map(|ix| generate_cpi_accounts_struct(&ix_struct_ident(&ix.name).to_string(), &ix.accounts));
+    let helpers = ixs.iter().map(generate_cpi_helper);
+    quote! {
+        use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+
+        pub mod accounts {
+            //! Accounts passed to the CPI helpers.
+            use super::*;
+            #(#accounts)*
+        }
+
+        #(#helpers)*
+    }
+}
@@ -1,5 +1,9 @@
 use anchor_lang::solana_program::hash::hash;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AnchorDeserialize;
+use base64::Engine;
 use heck::{ToPascalCase, ToSnakeCase};
+use std::fmt;
 
 pub fn get_type_name<'a, T: ?Sized + 'a>() -> String {
   let full_type_name = std::any::type_name::<T>();
@@ -9,25 +13,114 @@ pub fn get_type_name<'a, T: ?Sized + 'a>() -> String {
   }
 }
 
+/// Error returned when decoding accounts, instructions or events, or looking up their discriminators.
+#[derive(Debug)]
+pub enum DecodeError {
+  /// The data is shorter than the shortest discriminator it could start with.
+  TooShort { len: usize, min_len: usize },
+  /// The data does not start with the discriminator of any known type.
+  /// Holds the leading bytes of the data, up to the length of the longest discriminator.
+  UnknownDiscriminator(Vec<u8>),
+  /// No known type has the given name.
+  UnknownName(String),
+  /// The instruction was given fewer accounts than the IDL lists for it.
+  MissingAccounts { len: usize, min_len: usize },
+  /// The discriminator matched, but Borsh failed to deserialize the expected type.
+  /// `offset` is the position in the data, including the discriminator, at which deserialization stopped.
+  Borsh {
+    type_name: String,
+    offset: usize,
+    error: std::io::Error,
+  },
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      DecodeError::TooShort { len, min_len } => {
+        write!(f, "data is {} bytes, shorter than the {} byte discriminator", len, min_len)
+      }
+      DecodeError::UnknownDiscriminator(discrim) => write!(f, "unknown discriminator {:?}", discrim),
+      DecodeError::UnknownName(name) => write!(f, "unknown type name \"{}\"", name),
+      DecodeError::MissingAccounts { len, min_len } => {
+        write!(f, "instruction has {} accounts, fewer than the {} it takes", len, min_len)
+      }
+      DecodeError::Borsh { type_name, offset, error } => {
+        write!(f, "failed to deserialize {} at byte {}: {}", type_name, offset, error)
+      }
+    }
+  }
+}
+
+impl std::error::Error for DecodeError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      DecodeError::Borsh { error, .. } => Some(error),
+      _ => None,
+    }
+  }
+}
+
+/// Builds the error for data that matched none of the discriminators with the given lengths.
+pub fn unmatched_discriminator(data: &[u8], discrim_lens: &[usize]) -> DecodeError {
+  let min_len = discrim_lens.iter().copied().min().unwrap_or(0);
+  let max_len = discrim_lens.iter().copied().max().unwrap_or(0);
+  if data.len() < min_len {
+    DecodeError::TooShort { len: data.len(), min_len }
+  } else {
+    DecodeError::UnknownDiscriminator(data[..max_len.min(data.len())].to_vec())
+  }
+}
+
+/// Splits the account keys of an instruction into the `len` accounts listed by the IDL and the
+/// remaining ones.
+pub fn split_account_keys(keys: &[Pubkey], len: usize) -> std::result::Result<(&[Pubkey], &[Pubkey]), DecodeError> {
+  if keys.len() < len {
+    Err(DecodeError::MissingAccounts { len: keys.len(), min_len: len })
+  } else {
+    Ok(keys.split_at(len))
+  }
+}
+
+/// Deserializes `T` from the data following its discriminator of `discrim_len` bytes.
+/// If `exact` is set, trailing bytes after `T` are rejected as well.
+pub fn deserialize_after_discriminator<T: AnchorDeserialize>(
+  data: &[u8],
+  discrim_len: usize,
+  exact: bool,
+) -> std::result::Result<T, DecodeError> {
+  let mut buf = &data[discrim_len..];
+  let result = T::deserialize(&mut buf).and_then(|value| {
+    if exact && !buf.is_empty() {
+      Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Not all bytes read"))
+    } else {
+      Ok(value)
+    }
+  });
+  result.map_err(|error| DecodeError::Borsh {
+    type_name: get_type_name::<T>(),
+    offset: data.len() - buf.len(),
+    error,
+  })
+}
+
 pub trait Decode: Sized {
   /// Deserialize a program account into its defined (struct) type using Borsh.
   /// utf8 discriminator is the human-readable discriminator, such as "User", and usually the name
   /// of the struct marked with the #[account] Anchor macro that derives the Discriminator trait.
-  fn decode(data: &[u8]) -> std::result::Result<Self, Box<dyn std::error::Error>>;
+  fn decode(data: &[u8]) -> std::result::Result<Self, DecodeError>;
 }
 
 pub trait NameToDiscrim: Sized {
-  /// Deserialize a program account into its defined (struct) type using Borsh.
-  /// utf8 discriminator is the human-readable discriminator, such as "User", and usually the name
-  /// of the struct marked with the #[account] Anchor macro that derives the Discriminator trait.
-  fn name_to_discrim(name: &str) -> std::result::Result<[u8; 8], Box<dyn std::error::Error>>;
+  /// Looks up the discriminator of a type by its human-readable name, such as "User".
+  /// Discriminators are usually 8 bytes, but IDLs may declare discriminators of any length.
+  fn name_to_discrim(name: &str) -> std::result::Result<&'static [u8], DecodeError>;
 }
 
 pub trait DiscrimToName: Sized {
-  /// Deserialize a program account into its defined (struct) type using Borsh.
-  /// utf8 discriminator is the human-readable discriminator, such as "User", and usually the name
-  /// of the struct marked with the #[account] Anchor macro that derives the Discriminator trait.
-  fn discrim_to_name(discrim: [u8; 8]) -> std::result::Result<String, Box<dyn std::error::Error>>;
+  /// Looks up the human-readable name of the type whose discriminator prefixes `discrim`.
+  /// Either the discriminator alone or the full account or instruction data may be passed.
+  fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, DecodeError>;
 }
 
 /// Derives the account discriminator from the account name as Anchor does.
@@ -50,60 +143,124 @@ pub fn instruction_discriminator(name: &str) -> [u8; 8] {
   discriminator
 }
 
+/// Derives the event discriminator from the event name as Anchor does.
+/// Events are PascalCase.
+pub fn event_discriminator(name: &str) -> [u8; 8] {
+  let name = name.to_pascal_case();
+  let mut discriminator = [0u8; 8];
+  let hashed = hash(format!("event:{}", name).as_bytes()).to_bytes();
+  discriminator.copy_from_slice(&hashed[..8]);
+  discriminator
+}
+
+/// Prefix of the log line written by `sol_log_data`, which Anchor's `emit!` uses.
+pub const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Extracts the base64-decoded payloads of the `Program data:` lines logged by `program_id`.
+/// The invocation stack is tracked through the `invoke`/`success`/`failed` lines so that data
+/// logged by other programs during a CPI is skipped.
+pub fn program_data_from_logs<S: AsRef<str>>(program_id: &str, logs: &[S]) -> Vec<Vec<u8>> {
+  let mut stack: Vec<&str> = vec![];
+  let mut data = vec![];
+  for log in logs {
+    let log = log.as_ref();
+    if let Some(payload) = log.strip_prefix(PROGRAM_DATA_LOG_PREFIX) {
+      if stack.last() == Some(&program_id) {
+        if let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(payload) {
+          data.push(bytes);
+        }
+      }
+    } else if let Some(rest) = log.strip_prefix("Program ") {
+      let mut words = rest.split_whitespace();
+      match (words.next(), words.next()) {
+        (Some(id), Some("invoke")) => stack.push(id),
+        (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+          stack.pop();
+        }
+        _ => {}
+      }
+    }
+  }
+  data
+}
+
 #[macro_export]
 macro_rules! derive_account_type {
-    ($vis:vis enum $ident:ident {
-        $($variant:ident ($account_type:ty)),*$(,)?
-    }) => {
+    (@enum $(#[$attr:meta])* $vis:vis $ident:ident { $($variant:ident($account_type:ty)),* }) => {
         #[repr(C)]
         #[derive(anchor_lang::prelude::AnchorDeserialize, anchor_lang::prelude::AnchorSerialize)]
         #[derive(Clone)]
+        $(#[$attr])*
         $vis enum $ident {
             $($variant($account_type),)*
         }
 
+        impl $crate::NameToDiscrim for $ident {
+            fn name_to_discrim(name: &str) -> std::result::Result<&'static [u8], $crate::DecodeError> {
+                $(
+                  if name == $crate::get_type_name::<$account_type>() {
+                      return Ok(&<$account_type>::DISCRIMINATOR);
+                  }
+                )*
+                Err($crate::DecodeError::UnknownName(name.to_string()))
+            }
+        }
+    };
+    // Discriminators known at expansion time are dispatched with a single `match` on the leading bytes.
+    ($(#[$attr:meta])* $vis:vis enum $ident:ident {
+        $($variant:ident($account_type:ty) = [$($byte:literal),*]),*$(,)?
+    }) => {
+        $crate::derive_account_type!(@enum $(#[$attr])* $vis $ident { $($variant($account_type)),* });
+
         impl $crate::Decode for $ident {
-          fn decode(data: &[u8]) -> std::result::Result<Self, Box<dyn std::error::Error>> {
-            let discrim: &[u8; 8] = data[..8].try_into().map_err(|e| {
-              Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Instruction data is not 8 bytes or more".to_string()))
-            })?;
-            match discrim {
+          fn decode(data: &[u8]) -> std::result::Result<Self, $crate::DecodeError> {
+            match data {
               $(
-                $variant if discrim == &$crate::account_discriminator(&$crate::get_type_name::<$account_type>()) => {
-                    let acct = <$account_type>::try_from_slice(&data[8..])?;
-                    Ok(Self::$variant(acct.clone()))
-                },
+                [$($byte,)* ..] => {
+                  $crate::deserialize_after_discriminator::<$account_type>(data, <$account_type>::DISCRIMINATOR.len(), true).map(Self::$variant)
+                }
               )*
-              _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Invalid account discriminator".to_string())))
+              _ => Err($crate::unmatched_discriminator(data, &[$(<$account_type>::DISCRIMINATOR.len()),*])),
             }
           }
         }
 
-        impl $crate::NameToDiscrim for $ident {
-            fn name_to_discrim(name: &str) -> std::result::Result<[u8; 8], Box<dyn std::error::Error>> {
-                match name {
-                    $(
-                      $variant if name == $crate::get_type_name::<$account_type>() => {
-                          let discrim = $crate::account_discriminator(&$crate::get_type_name::<$account_type>());
-                          Ok(discrim)
-                      },
-                    )*
-                    _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Invalid account name".to_string())))
+        impl $crate::DiscrimToName for $ident {
+            fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, $crate::DecodeError> {
+                match discrim {
+                  $(
+                    [$($byte,)* ..] => Ok($crate::get_type_name::<$account_type>()),
+                  )*
+                  _ => Err($crate::unmatched_discriminator(discrim, &[$(<$account_type>::DISCRIMINATOR.len()),*])),
                 }
             }
         }
+    };
+    ($(#[$attr:meta])* $vis:vis enum $ident:ident {
+        $($variant:ident($account_type:ty)),*$(,)?
+    }) => {
+        $crate::derive_account_type!(@enum $(#[$attr])* $vis $ident { $($variant($account_type)),* });
+
+        impl $crate::Decode for $ident {
+          fn decode(data: &[u8]) -> std::result::Result<Self, $crate::DecodeError> {
+            $(
+              if data.starts_with(&<$account_type>::DISCRIMINATOR) {
+                  let acct = $crate::deserialize_after_discriminator::<$account_type>(data, <$account_type>::DISCRIMINATOR.len(), true)?;
+                  return Ok(Self::$variant(acct));
+              }
+            )*
+            Err($crate::unmatched_discriminator(data, &[$(<$account_type>::DISCRIMINATOR.len()),*]))
+          }
+        }
 
         impl $crate::DiscrimToName for $ident {
-            fn discrim_to_name(discrim: [u8; 8]) -> std::result::Result<String, Box<dyn std::error::Error>> {
-                match discrim {
-                    $(
-                      $variant if discrim == $crate::account_discriminator(&$crate::get_type_name::<$account_type>()) => {
-                          let name = $crate::get_type_name::<$account_type>();
-                          Ok(name)
-                      },
-                    )*
-                    _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Invalid account discriminator".to_string())))
-                }
+            fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, $crate::DecodeError> {
+                $(
+                  if discrim.starts_with(&<$account_type>::DISCRIMINATOR) {
+                      return Ok($crate::get_type_name::<$account_type>());
+                  }
+                )*
+                Err($crate::unmatched_discriminator(discrim, &[$(<$account_type>::DISCRIMINATOR.len()),*]))
             }
         }
     };
@@ -111,58 +268,175 @@ macro_rules! derive_account_type {
 
 #[macro_export]
 macro_rules! derive_instruction_type {
-    ($vis:vis enum $ident:ident {
-        $($variant:ident($ix_type:path)),*$(,)?
-    }) => {
+    (@enum $(#[$attr:meta])* $vis:vis $ident:ident { $($variant:ident($ix_type:path)),* }) => {
         // #[derive(Clone)]
         #[derive(anchor_lang::prelude::AnchorSerialize, anchor_lang::prelude::AnchorDeserialize)]
+        $(#[$attr])*
         $vis enum $ident {
             $($variant($ix_type),)*
         }
 
-        impl $crate::Decode for $ident {
-          fn decode(data: &[u8]) -> std::result::Result<Self, Box<dyn std::error::Error>> {
-            let discrim: &[u8; 8] = data[..8].try_into().map_err(|e| {
-              Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Instruction data is not 8 bytes or more".to_string()))
-            })?;
-            match discrim {
+        impl $crate::NameToDiscrim for $ident {
+            fn name_to_discrim(name: &str) -> std::result::Result<&'static [u8], $crate::DecodeError> {
                 $(
-                  $variant if discrim == &$crate::instruction_discriminator(&$crate::get_type_name::<$ix_type>()) => {
-                      let ix = <$ix_type>::deserialize(&mut &data[8..])?;
-                       Ok(Self::$variant(ix))
-                  },
+                  if name == $crate::get_type_name::<$ix_type>() {
+                      return Ok(&<$ix_type>::DISCRIMINATOR);
+                  }
                 )*
-                _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Invalid instruction discriminator".to_string())))
+                Err($crate::DecodeError::UnknownName(name.to_string()))
+            }
+        }
+    };
+    // Discriminators known at expansion time are dispatched with a single `match` on the leading bytes.
+    ($(#[$attr:meta])* $vis:vis enum $ident:ident {
+        $($variant:ident($ix_type:path) = [$($byte:literal),*]),*$(,)?
+    }) => {
+        $crate::derive_instruction_type!(@enum $(#[$attr])* $vis $ident { $($variant($ix_type)),* });
+
+        impl $crate::Decode for $ident {
+          fn decode(data: &[u8]) -> std::result::Result<Self, $crate::DecodeError> {
+            match data {
+              $(
+                [$($byte,)* ..] => {
+                  $crate::deserialize_after_discriminator::<$ix_type>(data, <$ix_type>::DISCRIMINATOR.len(), false).map(Self::$variant)
+                }
+              )*
+              _ => Err($crate::unmatched_discriminator(data, &[$(<$ix_type>::DISCRIMINATOR.len()),*])),
             }
           }
         }
 
+        impl $crate::DiscrimToName for $ident {
+            fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, $crate::DecodeError> {
+                match discrim {
+                  $(
+                    [$($byte,)* ..] => Ok($crate::get_type_name::<$ix_type>()),
+                  )*
+                  _ => Err($crate::unmatched_discriminator(discrim, &[$(<$ix_type>::DISCRIMINATOR.len()),*])),
+                }
+            }
+        }
+    };
+    ($(#[$attr:meta])* $vis:vis enum $ident:ident {
+        $($variant:ident($ix_type:path)),*$(,)?
+    }) => {
+        $crate::derive_instruction_type!(@enum $(#[$attr])* $vis $ident { $($variant($ix_type)),* });
+
+        impl $crate::Decode for $ident {
+          fn decode(data: &[u8]) -> std::result::Result<Self, $crate::DecodeError> {
+            $(
+              if data.starts_with(&<$ix_type>::DISCRIMINATOR) {
+                  let ix = $crate::deserialize_after_discriminator::<$ix_type>(data, <$ix_type>::DISCRIMINATOR.len(), false)?;
+                  return Ok(Self::$variant(ix));
+              }
+            )*
+            Err($crate::unmatched_discriminator(data, &[$(<$ix_type>::DISCRIMINATOR.len()),*]))
+          }
+        }
+
+        impl $crate::DiscrimToName for $ident {
+            fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, $crate::DecodeError> {
+                $(
+                  if discrim.starts_with(&<$ix_type>::DISCRIMINATOR) {
+                      return Ok($crate::get_type_name::<$ix_type>());
+                  }
+                )*
+                Err($crate::unmatched_discriminator(discrim, &[$(<$ix_type>::DISCRIMINATOR.len()),*]))
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! derive_event_type {
+    (@enum $(#[$attr:meta])* $vis:vis $ident:ident { $($variant:ident($event_type:ty)),* }) => {
+        #[derive(anchor_lang::prelude::AnchorSerialize, anchor_lang::prelude::AnchorDeserialize)]
+        #[derive(Clone, Debug)]
+        $(#[$attr])*
+        $vis enum $ident {
+            $($variant($event_type),)*
+        }
+
         impl $crate::NameToDiscrim for $ident {
-            fn name_to_discrim(name: &str) -> std::result::Result<[u8; 8], Box<dyn std::error::Error>> {
-                match name {
-                    $(
-                      $variant if name == $crate::get_type_name::<$ix_type>() => {
-                          let discrim = $crate::instruction_discriminator(&$crate::get_type_name::<$ix_type>());
-                          Ok(discrim)
-                      },
-                    )*
-                    _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Invalid instruction name".to_string())))
+            fn name_to_discrim(name: &str) -> std::result::Result<&'static [u8], $crate::DecodeError> {
+                $(
+                  if name == $crate::get_type_name::<$event_type>() {
+                      return Ok(&<$event_type>::DISCRIMINATOR);
+                  }
+                )*
+                Err($crate::DecodeError::UnknownName(name.to_string()))
+            }
+        }
+
+        impl $ident {
+            /// Decodes the events emitted by `program_id` from a transaction's log messages.
+            pub fn decode_logs<S: AsRef<str>>(
+                program_id: &str,
+                logs: &[S],
+            ) -> Vec<std::result::Result<Self, $crate::DecodeError>> {
+                $crate::program_data_from_logs(program_id, logs)
+                    .iter()
+                    .map(|data| <Self as $crate::Decode>::decode(data))
+                    .collect()
+            }
+        }
+    };
+    // Discriminators known at expansion time are dispatched with a single `match` on the leading bytes.
+    ($(#[$attr:meta])* $vis:vis enum $ident:ident {
+        $($variant:ident($event_type:ty) = [$($byte:literal),*]),*$(,)?
+    }) => {
+        $crate::derive_event_type!(@enum $(#[$attr])* $vis $ident { $($variant($event_type)),* });
+
+        impl $crate::Decode for $ident {
+          fn decode(data: &[u8]) -> std::result::Result<Self, $crate::DecodeError> {
+            match data {
+              $(
+                [$($byte,)* ..] => {
+                  $crate::deserialize_after_discriminator::<$event_type>(data, <$event_type>::DISCRIMINATOR.len(), false).map(Self::$variant)
                 }
+              )*
+              _ => Err($crate::unmatched_discriminator(data, &[$(<$event_type>::DISCRIMINATOR.len()),*])),
             }
+          }
         }
 
         impl $crate::DiscrimToName for $ident {
-            fn discrim_to_name(discrim: [u8; 8]) -> std::result::Result<String, Box<dyn std::error::Error>> {
+            fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, $crate::DecodeError> {
                 match discrim {
-                    $(
-                      $variant if discrim == $crate::instruction_discriminator(&$crate::get_type_name::<$ix_type>()) => {
-                          let name = $crate::get_type_name::<$ix_type>();
-                          Ok(name)
-                      },
-                    )*
-                    _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Invalid instruction discriminator".to_string())))
+                  $(
+                    [$($byte,)* ..] => Ok($crate::get_type_name::<$event_type>()),
+                  )*
+                  _ => Err($crate::unmatched_discriminator(discrim, &[$(<$event_type>::DISCRIMINATOR.len()),*])),
                 }
             }
         }
     };
-}
\ No newline at end of file
+    ($(#[$attr:meta])* $vis:vis enum $ident:ident {
+        $($variant:ident($event_type:ty)),*$(,)?
+    }) => {
+        $crate::derive_event_type!(@enum $(#[$attr])* $vis $ident { $($variant($event_type)),* });
+
+        impl $crate::Decode for $ident {
+          fn decode(data: &[u8]) -> std::result::Result<Self, $crate::DecodeError> {
+            $(
+              if data.starts_with(&<$event_type>::DISCRIMINATOR) {
+                  let event = $crate::deserialize_after_discriminator::<$event_type>(data, <$event_type>::DISCRIMINATOR.len(), false)?;
+                  return Ok(Self::$variant(event));
+              }
+            )*
+            Err($crate::unmatched_discriminator(data, &[$(<$event_type>::DISCRIMINATOR.len()),*]))
+          }
+        }
+
+        impl $crate::DiscrimToName for $ident {
+            fn discrim_to_name(discrim: &[u8]) -> std::result::Result<String, $crate::DecodeError> {
+                $(
+                  if discrim.starts_with(&<$event_type>::DISCRIMINATOR) {
+                      return Ok($crate::get_type_name::<$event_type>());
+                  }
+                )*
+                Err($crate::unmatched_discriminator(discrim, &[$(<$event_type>::DISCRIMINATOR.len()),*]))
+            }
+        }
+    };
+}
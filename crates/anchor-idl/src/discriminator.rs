@@ -0,0 +1,37 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{
+    account_discriminator, event_discriminator, instruction_discriminator, IdlEvent,
+    IdlInstruction, IdlTypeDefinition,
+};
+
+/// Returns the discriminator of an instruction, preferring the one declared by the IDL.
+pub fn idl_instruction_discriminator(ix: &IdlInstruction) -> Vec<u8> {
+    ix.discriminator
+        .clone()
+        .unwrap_or_else(|| instruction_discriminator(&ix.name).to_vec())
+}
+
+/// Returns the discriminator of an account, preferring the one declared by the IDL.
+pub fn idl_account_discriminator(def: &IdlTypeDefinition) -> Vec<u8> {
+    def.discriminator
+        .clone()
+        .unwrap_or_else(|| account_discriminator(&def.name).to_vec())
+}
+
+/// Returns the discriminator of an event, preferring the one declared by the IDL.
+pub fn idl_event_discriminator(event: &IdlEvent) -> Vec<u8> {
+    event
+        .discriminator
+        .clone()
+        .unwrap_or_else(|| event_discriminator(&event.name).to_vec())
+}
+
+/// Generates the `DISCRIMINATOR` constant of a type.
+pub fn generate_discriminator_const(discriminator: &[u8]) -> TokenStream {
+    let len = discriminator.len();
+    quote! {
+        pub const DISCRIMINATOR: [u8; #len] = [#(#discriminator),*];
+    }
+}
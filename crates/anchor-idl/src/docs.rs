@@ -0,0 +1,25 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Generates a `#[doc]` attribute for each line of the docs of an IDL item.
+pub fn generate_docs(docs: Option<&[String]>) -> TokenStream {
+    let lines = docs.unwrap_or_default().iter().map(|line| format!(" {}", line));
+    quote! {
+        #(#[doc = #lines])*
+    }
+}
+
+/// Generates the docs of an IDL item followed by an empty line, so that they make the summary
+/// of the docs generated after them.
+pub fn generate_leading_docs(docs: Option<&[String]>) -> TokenStream {
+    match docs {
+        Some(docs) if !docs.is_empty() => {
+            let docs = generate_docs(Some(docs));
+            quote! {
+                #docs
+                #[doc = ""]
+            }
+        }
+        _ => quote! {},
+    }
+}
@@ -0,0 +1,389 @@
+//! Decodes accounts, instructions and events to JSON at runtime, without generating code.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AnchorDeserialize;
+use serde_json::{Map, Number, Value as JsonValue};
+use std::io;
+
+use crate::{
+    idl_account_discriminator, idl_event_discriminator, idl_instruction_discriminator,
+    unmatched_discriminator, DecodeError, EnumFields, Idl, IdlField, IdlType, IdlTypeDefinition,
+    IdlTypeDefinitionTy,
+};
+
+/// A value decoded by a [DynamicDecoder], along with the name of its type in the IDL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoded {
+    pub name: String,
+    pub value: JsonValue,
+}
+
+/// Decodes Borsh-serialized accounts, instruction args and events of any program
+/// into [JsonValue]s by walking the types of its IDL.
+///
+/// Values are represented as Anchor's TypeScript client does: structs are objects keyed by
+/// field name, enums are objects with a single key naming the variant, public keys are base58
+/// strings and 128-bit integers are decimal strings.
+#[derive(Debug, Clone)]
+pub struct DynamicDecoder {
+    idl: Idl,
+    account_discriminators: Vec<Vec<u8>>,
+    instruction_discriminators: Vec<Vec<u8>>,
+    event_discriminators: Vec<Vec<u8>>,
+}
+
+impl DynamicDecoder {
+    pub fn new(idl: Idl) -> Self {
+        let account_discriminators = idl.accounts.iter().map(idl_account_discriminator).collect();
+        let instruction_discriminators = idl
+            .instructions
+            .iter()
+            .map(idl_instruction_discriminator)
+            .collect();
+        let event_discriminators = idl
+            .events
+            .iter()
+            .flatten()
+            .map(idl_event_discriminator)
+            .collect();
+        Self {
+            idl,
+            account_discriminators,
+            instruction_discriminators,
+            event_discriminators,
+        }
+    }
+
+    /// Creates a decoder from an IDL in either the legacy or the Anchor 0.30+ format.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        Ok(Self::new(crate::parse_idl(json)?))
+    }
+
+    pub fn idl(&self) -> &Idl {
+        &self.idl
+    }
+
+    /// Decodes the data of a program account, which must not have bytes left over.
+    pub fn decode_account(&self, data: &[u8]) -> Result<Decoded, DecodeError> {
+        let index = find_discriminator(&self.account_discriminators, data)?;
+        let def = &self.idl.accounts[index];
+        let discrim_len = self.account_discriminators[index].len();
+        self.decode_with(&def.name, data, discrim_len, true, |buf| {
+            self.decode_type_definition(def, buf)
+        })
+    }
+
+    /// Decodes the data of an instruction into an object of its args.
+    pub fn decode_instruction(&self, data: &[u8]) -> Result<Decoded, DecodeError> {
+        let index = find_discriminator(&self.instruction_discriminators, data)?;
+        let ix = &self.idl.instructions[index];
+        let discrim_len = self.instruction_discriminators[index].len();
+        self.decode_with(&ix.name, data, discrim_len, false, |buf| {
+            self.decode_fields(&ix.args, buf)
+        })
+    }
+
+    /// Decodes the data of an event, such as one returned by [crate::program_data_from_logs].
+    pub fn decode_event(&self, data: &[u8]) -> Result<Decoded, DecodeError> {
+        let index = find_discriminator(&self.event_discriminators, data)?;
+        let discrim = &self.event_discriminators[index];
+        let event = self
+            .idl
+            .events
+            .iter()
+            .flatten()
+            .nth(index)
+            .ok_or_else(|| DecodeError::UnknownDiscriminator(discrim.clone()))?;
+        self.decode_with(&event.name, data, discrim.len(), false, |buf| {
+            let mut map = Map::new();
+            for field in &event.fields {
+                map.insert(field.name.clone(), self.decode_type(&field.ty, buf)?);
+            }
+            Ok(JsonValue::Object(map))
+        })
+    }
+
+    /// Decodes a single value of type `ty`, advancing `buf` past it.
+    pub fn decode_type(&self, ty: &IdlType, buf: &mut &[u8]) -> io::Result<JsonValue> {
+        Ok(match ty {
+            IdlType::Bool => bool::deserialize(buf)?.into(),
+            IdlType::U8 => u8::deserialize(buf)?.into(),
+            IdlType::I8 => i8::deserialize(buf)?.into(),
+            IdlType::U16 => u16::deserialize(buf)?.into(),
+            IdlType::I16 => i16::deserialize(buf)?.into(),
+            IdlType::U32 => u32::deserialize(buf)?.into(),
+            IdlType::I32 => i32::deserialize(buf)?.into(),
+            IdlType::F32 => float(f32::deserialize(buf)?.into()),
+            IdlType::U64 => u64::deserialize(buf)?.into(),
+            IdlType::I64 => i64::deserialize(buf)?.into(),
+            IdlType::F64 => float(f64::deserialize(buf)?),
+            IdlType::U128 => u128::deserialize(buf)?.to_string().into(),
+            IdlType::I128 => i128::deserialize(buf)?.to_string().into(),
+            IdlType::Bytes => Vec::<u8>::deserialize(buf)?.into(),
+            IdlType::String => String::deserialize(buf)?.into(),
+            IdlType::PublicKey => Pubkey::deserialize(buf)?.to_string().into(),
+            IdlType::Option(inner) => match u8::deserialize(buf)? {
+                0 => JsonValue::Null,
+                1 => self.decode_type(inner, buf)?,
+                tag => return Err(invalid_data(format!("invalid Option tag {}", tag))),
+            },
+            IdlType::Vec(inner) => {
+                let len = u32::deserialize(buf)? as usize;
+                // Every element takes at least a byte, so a longer length can only be corrupt.
+                if len > buf.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("Vec of {} elements exceeds the remaining data", len),
+                    ));
+                }
+                JsonValue::Array(
+                    (0..len)
+                        .map(|_| self.decode_type(inner, buf))
+                        .collect::<io::Result<_>>()?,
+                )
+            }
+            IdlType::Array(inner, len) => JsonValue::Array(
+                (0..*len)
+                    .map(|_| self.decode_type(inner, buf))
+                    .collect::<io::Result<_>>()?,
+            ),
+            IdlType::Defined(name) => {
+                let def = self.find_type(name).ok_or_else(|| {
+                    invalid_data(format!("type {} is not defined by the IDL", name))
+                })?;
+                self.decode_type_definition(def, buf)?
+            }
+        })
+    }
+
+    fn decode_type_definition(
+        &self,
+        def: &IdlTypeDefinition,
+        buf: &mut &[u8],
+    ) -> io::Result<JsonValue> {
+        match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => self.decode_fields(fields, buf),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let tag = u8::deserialize(buf)?;
+                let variant = variants.get(tag as usize).ok_or_else(|| {
+                    invalid_data(format!("invalid variant {} of enum {}", tag, def.name))
+                })?;
+                let value = match &variant.fields {
+                    None => JsonValue::Object(Map::new()),
+                    Some(EnumFields::Named(fields)) => self.decode_fields(fields, buf)?,
+                    Some(EnumFields::Tuple(types)) => JsonValue::Array(
+                        types
+                            .iter()
+                            .map(|ty| self.decode_type(ty, buf))
+                            .collect::<io::Result<_>>()?,
+                    ),
+                };
+                let mut map = Map::new();
+                map.insert(variant.name.clone(), value);
+                Ok(JsonValue::Object(map))
+            }
+        }
+    }
+
+    fn decode_fields(&self, fields: &[IdlField], buf: &mut &[u8]) -> io::Result<JsonValue> {
+        let mut map = Map::new();
+        for field in fields {
+            map.insert(field.name.clone(), self.decode_type(&field.ty, buf)?);
+        }
+        Ok(JsonValue::Object(map))
+    }
+
+    fn find_type(&self, name: &str) -> Option<&IdlTypeDefinition> {
+        self.idl
+            .types
+            .iter()
+            .chain(self.idl.accounts.iter())
+            .find(|def| def.name == name)
+    }
+
+    /// Decodes the data following the discriminator. If `exact` is set, trailing bytes are
+    /// rejected as well, as they are by [crate::deserialize_after_discriminator].
+    fn decode_with(
+        &self,
+        name: &str,
+        data: &[u8],
+        discrim_len: usize,
+        exact: bool,
+        decode: impl FnOnce(&mut &[u8]) -> io::Result<JsonValue>,
+    ) -> Result<Decoded, DecodeError> {
+        let mut buf = &data[discrim_len..];
+        let value = decode(&mut buf).and_then(|value| {
+            if exact && !buf.is_empty() {
+                Err(invalid_data("Not all bytes read".to_string()))
+            } else {
+                Ok(value)
+            }
+        });
+        let value = value.map_err(|error| DecodeError::Borsh {
+            type_name: name.to_string(),
+            offset: data.len() - buf.len(),
+            error,
+        })?;
+        Ok(Decoded {
+            name: name.to_string(),
+            value,
+        })
+    }
+}
+
+fn find_discriminator(discriminators: &[Vec<u8>], data: &[u8]) -> Result<usize, DecodeError> {
+    discriminators
+        .iter()
+        .position(|discrim| data.starts_with(discrim))
+        .ok_or_else(|| {
+            let lens: Vec<usize> = discriminators.iter().map(Vec::len).collect();
+            unmatched_discriminator(data, &lens)
+        })
+}
+
+fn float(value: f64) -> JsonValue {
+    Number::from_f64(value).map_or(JsonValue::Null, JsonValue::Number)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AnchorSerialize;
+    use serde_json::json;
+
+    const IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "market",
+        "instructions": [{
+            "name": "placeOrder",
+            "accounts": [],
+            "args": [
+                {"name": "side", "type": {"defined": "Side"}},
+                {"name": "limit", "type": {"option": "u64"}}
+            ]
+        }],
+        "accounts": [{
+            "name": "Market",
+            "type": {"kind": "struct", "fields": [
+                {"name": "authority", "type": "publicKey"},
+                {"name": "fees", "type": {"defined": "Fees"}},
+                {"name": "lots", "type": {"vec": "u16"}},
+                {"name": "prices", "type": {"array": ["u32", 2]}},
+                {"name": "total", "type": "u128"}
+            ]}
+        }],
+        "types": [
+            {"name": "Fees", "type": {"kind": "struct", "fields": [
+                {"name": "maker", "type": "i8"},
+                {"name": "taker", "type": {"option": "u8"}}
+            ]}},
+            {"name": "Side", "type": {"kind": "enum", "variants": [
+                {"name": "Bid"},
+                {"name": "Ask", "fields": [{"name": "price", "type": "u64"}]},
+                {"name": "Range", "fields": ["u8", "bool"]}
+            ]}}
+        ],
+        "events": [{
+            "name": "OrderPlaced",
+            "fields": [{"name": "side", "type": {"defined": "Side"}, "index": false}]
+        }]
+    }"#;
+
+    fn decoder() -> DynamicDecoder {
+        DynamicDecoder::from_json(IDL).unwrap()
+    }
+
+    fn market_data(decoder: &DynamicDecoder) -> Vec<u8> {
+        let mut data = decoder.account_discriminators[0].clone();
+        let fields = (
+            Pubkey::new_from_array([1; 32]),
+            (-3i8, Some(5u8)),
+            vec![7u16, 8],
+            [9u32, 10],
+            u128::MAX,
+        );
+        fields.serialize(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn decodes_accounts() {
+        let decoder = decoder();
+        let decoded = decoder.decode_account(&market_data(&decoder)).unwrap();
+        assert_eq!(decoded.name, "Market");
+        assert_eq!(
+            decoded.value,
+            json!({
+                "authority": Pubkey::new_from_array([1; 32]).to_string(),
+                "fees": {"maker": -3, "taker": 5},
+                "lots": [7, 8],
+                "prices": [9, 10],
+                "total": u128::MAX.to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_account_bytes() {
+        let decoder = decoder();
+        let mut data = market_data(&decoder);
+        let len = data.len();
+        data.push(0);
+        match decoder.decode_account(&data) {
+            Err(DecodeError::Borsh {
+                type_name, offset, ..
+            }) => {
+                assert_eq!(type_name, "Market");
+                assert_eq!(offset, len);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn decodes_instructions() {
+        let decoder = decoder();
+        let mut data = decoder.instruction_discriminators[0].clone();
+        (1u8, 100u64, None::<u64>).serialize(&mut data).unwrap();
+        let decoded = decoder.decode_instruction(&data).unwrap();
+        assert_eq!(decoded.name, "placeOrder");
+        assert_eq!(
+            decoded.value,
+            json!({"side": {"Ask": {"price": 100}}, "limit": null})
+        );
+
+        let mut data = decoder.instruction_discriminators[0].clone();
+        (0u8, Some(3u64)).serialize(&mut data).unwrap();
+        let decoded = decoder.decode_instruction(&data).unwrap();
+        assert_eq!(decoded.value, json!({"side": {"Bid": {}}, "limit": 3}));
+    }
+
+    #[test]
+    fn decodes_events() {
+        let decoder = decoder();
+        let mut data = decoder.event_discriminators[0].clone();
+        (2u8, 4u8, true).serialize(&mut data).unwrap();
+        let decoded = decoder.decode_event(&data).unwrap();
+        assert_eq!(decoded.name, "OrderPlaced");
+        assert_eq!(decoded.value, json!({"side": {"Range": [4, true]}}));
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let decoder = decoder();
+        let mut data = decoder.event_discriminators[0].clone();
+        data.push(3);
+        assert!(matches!(
+            decoder.decode_event(&data),
+            Err(DecodeError::Borsh { offset: 9, .. })
+        ));
+        assert!(matches!(
+            decoder.decode_account(&[0; 8]),
+            Err(DecodeError::UnknownDiscriminator(_))
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{type_ident, IdlErrorCode};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Generates a single error variant.
+pub fn generate_error_variant(error: &IdlErrorCode) -> TokenStream {
+    let name = type_ident(&error.name);
+    let code = proc_macro2::Literal::u32_unsuffixed(error.code);
+    // `#[msg]` is rendered through `write!`, so braces must be escaped and
+    // quotes are stripped by Anchor's parser.
+    let msg = error.msg.as_ref().map(|msg| {
+        let msg = msg
+            .replace('{', "{{")
+            .replace('}', "}}")
+            .replace('"', "'");
+        quote! {
+            #[msg(#msg)]
+        }
+    });
+    quote! {
+        #msg
+        #name = #code
+    }
+}
+
+/// Generates the error enum and its lookup helpers.
+pub fn generate_errors(errors: &[IdlErrorCode]) -> TokenStream {
+    let variants = errors.iter().map(generate_error_variant);
+    let from_code_arms = errors.iter().map(|error| {
+        let name = type_ident(&error.name);
+        let code = proc_macro2::Literal::u32_unsuffixed(error.code);
+        quote! {
+            #code => Some(Self::#name)
+        }
+    });
+
+    let error_attr = if cfg!(feature = "compat-program-result") {
+        quote! { #[error(offset = 0)] }
+    } else {
+        quote! { #[error_code(offset = 0)] }
+    };
+
+    quote! {
+        #error_attr
+        #[derive(PartialEq, Eq)]
+        pub enum ErrorCode {
+            #(#variants),*
+        }
+
+        impl ErrorCode {
+            /// Looks up an error by its numeric code.
+            pub fn from_code(code: u32) -> Option<Self> {
+                match code {
+                    #(#from_code_arms,)*
+                    _ => None,
+                }
+            }
+
+            /// Looks up the error behind a [ProgramError::Custom].
+            pub fn from_program_error(err: &ProgramError) -> Option<Self> {
+                match err {
+                    ProgramError::Custom(code) => Self::from_code(*code),
+                    _ => None,
+                }
+            }
+
+            /// Looks up the error behind an `InstructionError::Custom`, such as the one
+            /// carried by a failed transaction's `TransactionError::InstructionError`.
+            pub fn from_instruction_error(
+                err: &anchor_lang::solana_program::instruction::InstructionError,
+            ) -> Option<Self> {
+                match err {
+                    anchor_lang::solana_program::instruction::InstructionError::Custom(code) => {
+                        Self::from_code(*code)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors() -> Vec<IdlErrorCode> {
+        serde_json::from_str(
+            r#"[{"code": 6000, "name": "InvalidAmount", "msg": "amount {0} is \"too\" large"},
+                {"code": 6003, "name": "type"}]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn variants_keep_their_codes_and_messages() {
+        let generated = generate_errors(&errors()).to_string();
+        let msg = "amount {{0}} is 'too' large";
+        let expected = quote! {
+            pub enum ErrorCode {
+                #[msg(#msg)]
+                InvalidAmount = 6000,
+                r#type = 6003
+            }
+        };
+        assert!(generated.contains(&expected.to_string()));
+    }
+
+    #[test]
+    fn codes_map_back_to_variants() {
+        let generated = generate_errors(&errors()).to_string();
+        let from_code = quote! {
+            match code {
+                6000 => Some(Self::InvalidAmount),
+                6003 => Some(Self::r#type),
+                _ => None,
+            }
+        };
+        assert!(generated.contains(&from_code.to_string()));
+        let from_program_error = quote! {
+            match err {
+                ProgramError::Custom(code) => Self::from_code(*code),
+                _ => None,
+            }
+        };
+        assert!(generated.contains(&from_program_error.to_string()));
+    }
+}
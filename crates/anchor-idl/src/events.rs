@@ -0,0 +1,58 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::{
+    generate_discriminator_const, generate_docs, generate_serde_derives, generate_serde_field_attr,
+    idl_event_discriminator, type_ident, unique_field_idents, IdlEvent, SerdeOpts,
+};
+
+/// Generates a single event struct.
+pub fn generate_event(event: &IdlEvent, serde: Option<SerdeOpts>) -> TokenStream {
+    let struct_name = type_ident(&event.name);
+    let alias = struct_name.doc_alias();
+    let names = unique_field_idents(event.fields.iter().map(|field| field.name.as_str()));
+    let fields_rendered = event.fields.iter().zip(names).map(|(field, name)| {
+        let docs = generate_docs(field.docs.as_deref());
+        let field_alias = name.doc_alias();
+        let serde_attr = generate_serde_field_attr(&field.ty, serde);
+        let type_name = crate::ty_to_rust_type(&field.ty);
+        let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
+        let index = if field.index {
+            quote! { #[index] }
+        } else {
+            quote! {}
+        };
+        quote! {
+            #docs
+            #index
+            #field_alias
+            #serde_attr
+            pub #name: #stream
+        }
+    });
+    let doc = format!(" Event: {}", event.name);
+    let discriminator = generate_discriminator_const(&idl_event_discriminator(event));
+    let derive_serde = generate_serde_derives(serde);
+    quote! {
+        #[event]
+        #[doc = #doc]
+        #alias
+        #[derive(Clone, Debug)]
+        #derive_serde
+        pub struct #struct_name {
+            #(#fields_rendered),*
+        }
+
+        impl #struct_name {
+            #discriminator
+        }
+    }
+}
+
+/// Generates all event structs.
+pub fn generate_events(events: &[IdlEvent], serde: Option<SerdeOpts>) -> TokenStream {
+    let streams = events.iter().map(|event| generate_event(event, serde));
+    quote! {
+        #(#streams)*
+    }
+}
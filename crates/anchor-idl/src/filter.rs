@@ -0,0 +1,34 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Generates the `AccountFilter` enum returned by the filters of the account types.
+///
+/// It is generated into each crate rather than shared, so that generated crates don't depend on
+/// any particular RPC client, or on anything besides Anchor.
+pub fn generate_account_filter() -> TokenStream {
+    quote! {
+        /// A filter on the accounts returned by `getProgramAccounts`.
+        ///
+        /// Convert it to the filter type of your RPC client, such as `solana-client`'s
+        /// `RpcFilterType`.
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum AccountFilter {
+            /// Matches accounts whose data contains `bytes` at `offset`.
+            Memcmp { offset: usize, bytes: Vec<u8> },
+            /// Matches accounts whose data is exactly this many bytes long.
+            DataSize(u64),
+        }
+
+        impl AccountFilter {
+            /// Returns true if an account with this data passes the filter.
+            pub fn matches(&self, data: &[u8]) -> bool {
+                match self {
+                    AccountFilter::Memcmp { offset, bytes } => data
+                        .get(*offset..)
+                        .map_or(false, |data| data.starts_with(bytes)),
+                    AccountFilter::DataSize(len) => data.len() as u64 == *len,
+                }
+            }
+        }
+    }
+}
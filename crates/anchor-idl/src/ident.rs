@@ -0,0 +1,228 @@
+//! Converts names from the IDL into valid Rust identifiers.
+//!
+//! Names which are Rust keywords become raw identifiers, such as `r#type`. The few keywords which
+//! cannot be raw (`self`, `Self`, `super`, `crate`) and names containing characters that are not
+//! allowed in identifiers are renamed instead, keeping the IDL name as a `#[doc(alias)]`.
+
+use std::collections::HashSet;
+
+use heck::{ToPascalCase, ToSnakeCase};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, ToTokens};
+
+/// An identifier generated from a name in the IDL.
+#[derive(Clone, Debug)]
+pub struct GenIdent {
+    pub ident: Ident,
+    /// The name from the IDL, if the identifier had to be renamed.
+    pub alias: Option<String>,
+}
+
+impl GenIdent {
+    /// Generates a `#[doc(alias)]` attribute for the IDL name of a renamed identifier.
+    pub fn doc_alias(&self) -> TokenStream {
+        match &self.alias {
+            Some(alias) => quote! { #[doc(alias = #alias)] },
+            None => quote! {},
+        }
+    }
+
+    /// Returns the identifier without its `r#` prefix.
+    pub fn unraw(&self) -> String {
+        let ident = self.ident.to_string();
+        ident.strip_prefix("r#").map(str::to_string).unwrap_or(ident)
+    }
+}
+
+impl ToTokens for GenIdent {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.ident.to_tokens(tokens)
+    }
+}
+
+/// Generates the identifier of a field, argument or account, which are snake_case.
+pub fn field_ident(name: &str) -> GenIdent {
+    make_ident(name, &name.to_snake_case(), true)
+}
+
+/// Generates the identifier of a type or enum variant, which keep the case of the IDL.
+pub fn type_ident(name: &str) -> GenIdent {
+    make_ident(name, name, true)
+}
+
+/// Generates the identifier of an instruction's functions, which are snake_case.
+///
+/// Keywords are renamed rather than made raw, since Anchor's `#[program]` derives the names of
+/// the instruction structs from the function names.
+pub fn fn_ident(name: &str) -> GenIdent {
+    make_ident(name, &name.to_snake_case(), false)
+}
+
+/// Generates the identifier of the structs of an instruction, as Anchor derives them from the
+/// name of the instruction's function in `#[program]`.
+pub fn ix_struct_ident(name: &str) -> Ident {
+    Ident::new(&fn_ident(name).unraw().to_pascal_case(), Span::call_site())
+}
+
+/// Converts a defined type to its Rust representation. Paths, such as the ones of a type map,
+/// are kept as they are.
+pub fn type_path(name: &str) -> String {
+    match syn::parse_str::<syn::Path>(name) {
+        Ok(path) if path.get_ident().is_none() => name.to_string(),
+        _ => type_ident(name).ident.to_string(),
+    }
+}
+
+/// Generates the identifiers of a list of fields, renaming the ones which would collide.
+pub fn unique_field_idents<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<GenIdent> {
+    unique_field_idents_excluding(names, &[])
+}
+
+/// Generates the identifiers of a list of fields, renaming the ones which would collide with
+/// each other or with one of the `reserved` names.
+pub fn unique_field_idents_excluding<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+    reserved: &[&str],
+) -> Vec<GenIdent> {
+    let mut used: HashSet<String> = reserved.iter().map(|name| name.to_string()).collect();
+    names
+        .into_iter()
+        .map(|name| {
+            let mut gen = field_ident(name);
+            let base = gen.unraw();
+            let mut n = 1;
+            while !used.insert(gen.unraw()) {
+                gen = GenIdent {
+                    ident: Ident::new(&format!("{}_{}", base, n), Span::call_site()),
+                    alias: Some(name.to_string()).filter(|alias| is_valid_alias(alias)),
+                };
+                n += 1;
+            }
+            gen
+        })
+        .collect()
+}
+
+fn make_ident(name: &str, converted: &str, allow_raw: bool) -> GenIdent {
+    let mut sanitized: String = converted
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    if sanitized == "_" {
+        sanitized.push('_');
+    }
+
+    if syn::parse_str::<Ident>(&sanitized).is_ok() {
+        let alias = (sanitized != converted).then(|| name.to_string());
+        return GenIdent {
+            ident: Ident::new(&sanitized, Span::call_site()),
+            alias: alias.filter(|alias| is_valid_alias(alias)),
+        };
+    }
+
+    // The name is a keyword.
+    if allow_raw && !matches!(sanitized.as_str(), "self" | "Self" | "super" | "crate") {
+        GenIdent {
+            ident: Ident::new_raw(&sanitized, Span::call_site()),
+            alias: None,
+        }
+    } else {
+        GenIdent {
+            ident: Ident::new(&format!("{}_", sanitized), Span::call_site()),
+            alias: Some(name.to_string()).filter(|alias| is_valid_alias(alias)),
+        }
+    }
+}
+
+/// Returns true if the name can be used in `#[doc(alias)]`, which rejects quotes and whitespace.
+fn is_valid_alias(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idents(gens: &[GenIdent]) -> Vec<(String, Option<&str>)> {
+        gens.iter()
+            .map(|gen| (gen.ident.to_string(), gen.alias.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn keywords_are_raw_unless_they_cannot_be() {
+        let gen = make_ident("type", "type", true);
+        assert_eq!(gen.ident.to_string(), "r#type");
+        assert_eq!(gen.alias, None);
+        assert_eq!(gen.unraw(), "type");
+
+        let gen = make_ident("type", "type", false);
+        assert_eq!(gen.ident.to_string(), "type_");
+        assert_eq!(gen.alias.as_deref(), Some("type"));
+
+        for keyword in ["self", "Self", "super", "crate"] {
+            let gen = make_ident(keyword, keyword, true);
+            assert_eq!(gen.ident.to_string(), format!("{}_", keyword));
+            assert_eq!(gen.alias.as_deref(), Some(keyword));
+        }
+    }
+
+    #[test]
+    fn invalid_characters_are_replaced() {
+        let gen = make_ident("1stPrice", "1st_price", true);
+        assert_eq!(gen.ident.to_string(), "_1st_price");
+        assert_eq!(gen.alias.as_deref(), Some("1stPrice"));
+
+        let gen = make_ident("fee-rate", "fee-rate", true);
+        assert_eq!(gen.ident.to_string(), "fee_rate");
+        assert_eq!(gen.alias.as_deref(), Some("fee-rate"));
+
+        let gen = make_ident("", "", true);
+        assert_eq!(gen.ident.to_string(), "__");
+        assert_eq!(gen.alias, None);
+
+        let gen = make_ident("my \"field\"", "my \"field\"", true);
+        assert_eq!(gen.ident.to_string(), "my__field_");
+        assert_eq!(gen.alias, None);
+    }
+
+    #[test]
+    fn colliding_fields_are_numbered() {
+        assert_eq!(
+            idents(&unique_field_idents(["myField", "my_field", "MyField"])),
+            [
+                ("my_field".to_string(), None),
+                ("my_field_1".to_string(), Some("my_field")),
+                ("my_field_2".to_string(), Some("MyField")),
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_fields_keep_only_valid_aliases() {
+        assert_eq!(
+            idents(&unique_field_idents(["my_field", "my field"])),
+            [
+                ("my_field".to_string(), None),
+                ("my_field_1".to_string(), None)
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_names_are_not_used() {
+        assert_eq!(
+            idents(&unique_field_idents_excluding(
+                ["data", "type"],
+                &["data", "type"]
+            )),
+            [
+                ("data_1".to_string(), Some("data")),
+                ("type_1".to_string(), Some("type")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,836 @@
+//! The Anchor 0.30+ IDL specification and its conversion into the generator's [super::Idl].
+//!
+//! Types are kept as raw JSON here and resolved during conversion: aliases are inlined and
+//! generic types are monomorphized into one definition per set of type arguments.
+
+use std::collections::{BTreeMap, HashSet};
+
+use heck::ToPascalCase;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use super::{IdlErrorCode, IdlRepr, IdlSerialization, IdlType};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idl {
+    pub address: String,
+    pub metadata: JsonValue,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccount>,
+    #[serde(default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(default)]
+    pub errors: Vec<IdlErrorCode>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDef>,
+    #[serde(default)]
+    pub constants: Vec<IdlConst>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    pub discriminator: Vec<u8>,
+    pub accounts: Vec<IdlInstructionAccountItem>,
+    pub args: Vec<IdlField>,
+    #[serde(default)]
+    pub returns: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlInstructionAccountItem {
+    Composite(IdlInstructionAccounts),
+    Single(IdlInstructionAccount),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstructionAccounts {
+    pub name: String,
+    pub accounts: Vec<IdlInstructionAccountItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstructionAccount {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(default)]
+    pub writable: bool,
+    #[serde(default)]
+    pub signer: bool,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub pda: Option<IdlPda>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlPda {
+    pub seeds: Vec<IdlSeed>,
+    #[serde(default)]
+    pub program: Option<IdlSeed>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlSeed {
+    Const {
+        value: Vec<u8>,
+    },
+    Arg {
+        path: String,
+    },
+    Account {
+        path: String,
+        #[serde(default)]
+        account: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(rename = "type")]
+    pub ty: JsonValue,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlAccount {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    pub discriminator: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlConst {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(rename = "type")]
+    pub ty: JsonValue,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    #[serde(default)]
+    pub docs: Vec<String>,
+    #[serde(default)]
+    pub serialization: Option<IdlSerialization>,
+    #[serde(default)]
+    pub repr: Option<IdlRepr>,
+    #[serde(default)]
+    pub generics: Vec<IdlTypeDefGeneric>,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefTy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefGeneric {
+    Type {
+        name: String,
+    },
+    Const {
+        name: String,
+        #[serde(rename = "type")]
+        ty: String,
+    },
+}
+
+impl IdlTypeDefGeneric {
+    pub fn name(&self) -> &str {
+        match self {
+            IdlTypeDefGeneric::Type { name } | IdlTypeDefGeneric::Const { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefTy {
+    Struct {
+        #[serde(default)]
+        fields: Option<IdlDefinedFields>,
+    },
+    Enum {
+        variants: Vec<IdlEnumVariant>,
+    },
+    Type {
+        alias: JsonValue,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlDefinedFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<JsonValue>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Option<IdlDefinedFields>,
+}
+
+/// A concrete argument substituted for a generic parameter.
+#[derive(Debug, Clone)]
+enum GenericArg {
+    Type(IdlType),
+    Const(String),
+}
+
+type Generics = BTreeMap<String, GenericArg>;
+
+fn docs(docs: &[String]) -> Option<Vec<String>> {
+    if docs.is_empty() {
+        None
+    } else {
+        Some(docs.to_vec())
+    }
+}
+
+/// Resolves raw JSON types against the IDL's type definitions.
+struct Converter<'a> {
+    typedefs: BTreeMap<&'a str, &'a IdlTypeDef>,
+    instantiated: HashSet<String>,
+    instances: Vec<super::IdlTypeDefinition>,
+}
+
+impl<'a> Converter<'a> {
+    fn convert_type(&mut self, value: &JsonValue, generics: &Generics) -> Result<IdlType, String> {
+        if let Some(name) = value.as_str() {
+            return Ok(match name {
+                "bool" => IdlType::Bool,
+                "u8" => IdlType::U8,
+                "i8" => IdlType::I8,
+                "u16" => IdlType::U16,
+                "i16" => IdlType::I16,
+                "u32" => IdlType::U32,
+                "i32" => IdlType::I32,
+                "f32" => IdlType::F32,
+                "u64" => IdlType::U64,
+                "i64" => IdlType::I64,
+                "f64" => IdlType::F64,
+                "u128" => IdlType::U128,
+                "i128" => IdlType::I128,
+                "bytes" => IdlType::Bytes,
+                "string" => IdlType::String,
+                "pubkey" | "publicKey" => IdlType::PublicKey,
+                _ => return Err(format!("unsupported type `{}`", name)),
+            });
+        }
+        let mut entries = value.as_object().into_iter().flatten();
+        let (kind, inner) = match (entries.next(), entries.next()) {
+            (Some(entry), None) => entry,
+            _ => return Err(format!("invalid type `{}`", value)),
+        };
+        match kind.as_str() {
+            "option" => Ok(IdlType::Option(Box::new(
+                self.convert_type(inner, generics)?,
+            ))),
+            "vec" => Ok(IdlType::Vec(Box::new(self.convert_type(inner, generics)?))),
+            "array" => {
+                let (ty, len) = match inner.as_array().map(|array| array.as_slice()) {
+                    Some([ty, len]) => (ty, len),
+                    _ => return Err(format!("invalid array type `{}`", value)),
+                };
+                let ty = self.convert_type(ty, generics)?;
+                let len = match len.get("generic").and_then(|name| name.as_str()) {
+                    Some(name) => match generics.get(name) {
+                        Some(GenericArg::Const(len)) => len
+                            .parse::<usize>()
+                            .map_err(|_| format!("invalid array length `{}`", len))?,
+                        _ => return Err(format!("unknown const generic `{}`", name)),
+                    },
+                    None => len
+                        .as_u64()
+                        .ok_or_else(|| format!("invalid array length `{}`", len))?
+                        as usize,
+                };
+                Ok(IdlType::Array(Box::new(ty), len))
+            }
+            "generic" => {
+                let name = inner.as_str().unwrap_or_default();
+                match generics.get(name) {
+                    Some(GenericArg::Type(ty)) => Ok(ty.clone()),
+                    _ => Err(format!("unknown type generic `{}`", name)),
+                }
+            }
+            "defined" => {
+                if let Some(name) = inner.as_str() {
+                    return self.resolve_defined(name, vec![]);
+                }
+                let name = inner
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .ok_or_else(|| format!("invalid defined type `{}`", value))?;
+                let mut args = vec![];
+                let raw_args = inner.get("generics").and_then(|args| args.as_array());
+                for arg in raw_args.into_iter().flatten() {
+                    match arg.get("kind").and_then(|kind| kind.as_str()) {
+                        Some("type") => {
+                            let ty = arg.get("type").unwrap_or(&JsonValue::Null);
+                            args.push(GenericArg::Type(self.convert_type(ty, generics)?));
+                        }
+                        Some("const") => {
+                            let value = arg.get("value").and_then(|value| value.as_str());
+                            args.push(GenericArg::Const(value.unwrap_or_default().to_string()));
+                        }
+                        _ => return Err(format!("invalid generic argument `{}`", arg)),
+                    }
+                }
+                self.resolve_defined(name, args)
+            }
+            _ => Err(format!("unsupported type `{}`", value)),
+        }
+    }
+
+    /// Resolves a reference to a defined type, inlining aliases and instantiating generics.
+    fn resolve_defined(&mut self, name: &str, args: Vec<GenericArg>) -> Result<IdlType, String> {
+        let def = match self.typedefs.get(name) {
+            Some(def) => *def,
+            None => return Ok(IdlType::Defined(name.to_string())),
+        };
+        if def.generics.len() != args.len() {
+            return Err(format!(
+                "type `{}` expects {} generic arguments, got {}",
+                name,
+                def.generics.len(),
+                args.len()
+            ));
+        }
+        let generics: Generics = def
+            .generics
+            .iter()
+            .map(|generic| generic.name().to_string())
+            .zip(args.iter().cloned())
+            .collect();
+        if let IdlTypeDefTy::Type { alias } = &def.ty {
+            return self.convert_type(alias, &generics);
+        }
+        if args.is_empty() {
+            return Ok(IdlType::Defined(name.to_string()));
+        }
+
+        let instance_name = format!(
+            "{}{}",
+            name,
+            args.iter()
+                .map(|arg| match arg {
+                    GenericArg::Type(ty) => crate::ty_to_rust_type(ty)
+                        .replace(|c: char| !c.is_alphanumeric(), " ")
+                        .to_pascal_case(),
+                    GenericArg::Const(value) => value.clone(),
+                })
+                .collect::<String>()
+        );
+        if self.instantiated.insert(instance_name.clone()) {
+            let instance = self.convert_typedef(def, &instance_name, &generics)?;
+            self.instances.push(instance);
+        }
+        Ok(IdlType::Defined(instance_name))
+    }
+
+    fn convert_instruction(&mut self, ix: &IdlInstruction) -> Result<super::IdlInstruction, String> {
+        let no_generics = Generics::new();
+        Ok(super::IdlInstruction {
+            name: ix.name.clone(),
+            docs: docs(&ix.docs),
+            discriminator: Some(ix.discriminator.clone()),
+            accounts: ix.accounts.iter().map(convert_account_item).collect(),
+            args: ix
+                .args
+                .iter()
+                .map(|arg| self.convert_field(arg, &no_generics))
+                .collect::<Result<Vec<_>, _>>()?,
+            returns: ix
+                .returns
+                .as_ref()
+                .map(|ty| self.convert_type(ty, &no_generics))
+                .transpose()?,
+        })
+    }
+
+    fn convert_field(&mut self, field: &IdlField, generics: &Generics) -> Result<super::IdlField, String> {
+        Ok(super::IdlField {
+            name: field.name.clone(),
+            docs: docs(&field.docs),
+            ty: self
+                .convert_type(&field.ty, generics)
+                .map_err(|e| format!("field `{}`: {}", field.name, e))?,
+        })
+    }
+
+    fn convert_typedef(
+        &mut self,
+        def: &IdlTypeDef,
+        name: &str,
+        generics: &Generics,
+    ) -> Result<super::IdlTypeDefinition, String> {
+        let ty = match &def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                let fields = match fields {
+                    Some(IdlDefinedFields::Named(fields)) => fields
+                        .iter()
+                        .map(|field| self.convert_field(field, generics))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    Some(IdlDefinedFields::Tuple(types)) => types
+                        .iter()
+                        .enumerate()
+                        .map(|(i, ty)| {
+                            Ok(super::IdlField {
+                                name: format!("field_{}", i),
+                                docs: None,
+                                ty: self.convert_type(ty, generics)?,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, String>>()?,
+                    None => vec![],
+                };
+                super::IdlTypeDefinitionTy::Struct { fields }
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let fields = match &variant.fields {
+                            Some(IdlDefinedFields::Named(fields)) => {
+                                Some(super::EnumFields::Named(
+                                    fields
+                                        .iter()
+                                        .map(|field| self.convert_field(field, generics))
+                                        .collect::<Result<Vec<_>, _>>()?,
+                                ))
+                            }
+                            Some(IdlDefinedFields::Tuple(types)) => {
+                                Some(super::EnumFields::Tuple(
+                                    types
+                                        .iter()
+                                        .map(|ty| self.convert_type(ty, generics))
+                                        .collect::<Result<Vec<_>, _>>()?,
+                                ))
+                            }
+                            None => None,
+                        };
+                        Ok(super::IdlEnumVariant {
+                            name: variant.name.clone(),
+                            fields,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                super::IdlTypeDefinitionTy::Enum { variants }
+            }
+            IdlTypeDefTy::Type { .. } => {
+                return Err(format!("type alias `{}` cannot be defined", name));
+            }
+        };
+        Ok(super::IdlTypeDefinition {
+            name: name.to_string(),
+            docs: docs(&def.docs),
+            discriminator: None,
+            serialization: def.serialization.clone(),
+            repr: def.repr.clone(),
+            ty,
+        })
+    }
+}
+
+fn convert_seed(seed: &IdlSeed) -> super::IdlSeed {
+    match seed {
+        IdlSeed::Const { value } => super::IdlSeed::Const(super::IdlSeedConst {
+            ty: None,
+            value: JsonValue::from(value.clone()),
+        }),
+        IdlSeed::Arg { path } => super::IdlSeed::Arg(super::IdlSeedArg {
+            ty: None,
+            path: path.clone(),
+        }),
+        IdlSeed::Account { path, account } => super::IdlSeed::Account(super::IdlSeedAccount {
+            ty: None,
+            account: account.clone(),
+            path: path.clone(),
+        }),
+    }
+}
+
+fn convert_account_item(item: &IdlInstructionAccountItem) -> super::IdlAccountItem {
+    match item {
+        IdlInstructionAccountItem::Single(account) => {
+            super::IdlAccountItem::IdlAccount(super::IdlAccount {
+                name: account.name.clone(),
+                is_mut: account.writable,
+                is_signer: account.signer,
+                is_optional: if account.optional { Some(true) } else { None },
+                docs: docs(&account.docs),
+                address: account.address.clone(),
+                pda: account.pda.as_ref().map(|pda| super::IdlPda {
+                    seeds: pda.seeds.iter().map(convert_seed).collect(),
+                    program_id: pda.program.as_ref().map(convert_seed),
+                }),
+            })
+        }
+        IdlInstructionAccountItem::Composite(accounts) => {
+            super::IdlAccountItem::IdlAccounts(super::IdlAccounts {
+                name: accounts.name.clone(),
+                accounts: accounts.accounts.iter().map(convert_account_item).collect(),
+            })
+        }
+    }
+}
+
+impl Idl {
+    /// Converts the IDL into the generator's [super::Idl].
+    pub fn try_into_idl(self) -> Result<super::Idl, serde_json::Error> {
+        self.convert().map_err(<serde_json::Error as serde::de::Error>::custom)
+    }
+
+    fn convert(&self) -> Result<super::Idl, String> {
+        let metadata_str = |key: &str| {
+            self.metadata
+                .get(key)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_string())
+                .ok_or_else(|| format!("missing `metadata.{}`", key))
+        };
+        let name = metadata_str("name")?;
+        let version = metadata_str("version")?;
+
+        let mut converter = Converter {
+            typedefs: self.types.iter().map(|def| (def.name.as_str(), def)).collect(),
+            instantiated: HashSet::new(),
+            instances: vec![],
+        };
+        let no_generics = Generics::new();
+
+        let mut types = vec![];
+        for def in &self.types {
+            if !def.generics.is_empty() || matches!(def.ty, IdlTypeDefTy::Type { .. }) {
+                continue;
+            }
+            let converted = converter
+                .convert_typedef(def, &def.name, &no_generics)
+                .map_err(|e| format!("type `{}`: {}", def.name, e))?;
+            types.push(converted);
+        }
+
+        let instructions = self
+            .instructions
+            .iter()
+            .map(|ix| {
+                converter
+                    .convert_instruction(ix)
+                    .map_err(|e| format!("instruction `{}`: {}", ix.name, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let constants = self
+            .constants
+            .iter()
+            .map(|constant| {
+                Ok(super::IdlConst {
+                    name: constant.name.clone(),
+                    docs: docs(&constant.docs),
+                    ty: converter
+                        .convert_type(&constant.ty, &no_generics)
+                        .map_err(|e| format!("constant `{}`: {}", constant.name, e))?,
+                    value: constant.value.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        types.append(&mut converter.instances);
+
+        // Account and event definitions live in `types`; move them out so they are
+        // only generated once.
+        let mut take_type = |name: &str| {
+            types
+                .iter()
+                .position(|def| def.name == name)
+                .map(|idx| types.remove(idx))
+                .ok_or_else(|| format!("missing type definition for `{}`", name))
+        };
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|account| {
+                let mut def = take_type(&account.name)?;
+                def.discriminator = Some(account.discriminator.clone());
+                Ok(def)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let events = self
+            .events
+            .iter()
+            .map(|event| {
+                let def = take_type(&event.name)?;
+                let fields = match def.ty {
+                    super::IdlTypeDefinitionTy::Struct { fields } => fields,
+                    super::IdlTypeDefinitionTy::Enum { .. } => {
+                        return Err(format!("event `{}` must be a struct", event.name));
+                    }
+                };
+                Ok(super::IdlEvent {
+                    name: event.name.clone(),
+                    discriminator: Some(event.discriminator.clone()),
+                    fields: fields
+                        .into_iter()
+                        .map(|field| super::IdlEventField {
+                            name: field.name,
+                            docs: field.docs,
+                            ty: field.ty,
+                            index: false,
+                        })
+                        .collect(),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(super::Idl {
+            version,
+            name,
+            address: Some(self.address.clone()),
+            docs: docs(&self.docs),
+            constants,
+            instructions,
+            state: None,
+            accounts,
+            types,
+            events: if events.is_empty() { None } else { Some(events) },
+            errors: if self.errors.is_empty() {
+                None
+            } else {
+                Some(self.errors.clone())
+            },
+            metadata: Some(self.metadata.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        parse_idl, EnumFields, IdlAccountItem, IdlEventField, IdlField, IdlSeed,
+        IdlTypeDefinitionTy,
+    };
+    use super::*;
+
+    const COUNTER_IDL: &str = include_str!("../../../../examples/counter-v030/idl.json");
+
+    fn field(name: &str, ty: IdlType) -> IdlField {
+        IdlField {
+            name: name.to_string(),
+            docs: None,
+            ty,
+        }
+    }
+
+    fn struct_fields(idl: &super::super::Idl, name: &str) -> Vec<IdlField> {
+        let def = idl
+            .accounts
+            .iter()
+            .chain(&idl.types)
+            .find(|def| def.name == name)
+            .unwrap();
+        match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => fields.clone(),
+            IdlTypeDefinitionTy::Enum { .. } => panic!("{} is an enum", name),
+        }
+    }
+
+    #[test]
+    fn converts_counter_idl() {
+        let idl = parse_idl(COUNTER_IDL).unwrap();
+        assert_eq!(idl.name, "counter");
+        assert_eq!(idl.version, "0.1.0");
+        assert_eq!(
+            idl.address.as_deref(),
+            Some("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS")
+        );
+
+        let initialize = &idl.instructions[0];
+        assert_eq!(
+            initialize.discriminator,
+            Some(vec![175, 175, 109, 31, 13, 152, 155, 237])
+        );
+        assert_eq!(
+            initialize.docs,
+            Some(vec!["Creates a counter owned by `authority`.".to_string()])
+        );
+        assert_eq!(
+            initialize.args,
+            [field(
+                "params",
+                IdlType::Defined("InitializeParams".to_string())
+            )]
+        );
+        let counter = match &initialize.accounts[0] {
+            IdlAccountItem::IdlAccount(account) => account,
+            IdlAccountItem::IdlAccounts(_) => panic!("counter is a group"),
+        };
+        assert!(counter.is_mut && !counter.is_signer);
+        let seeds = &counter.pda.as_ref().unwrap().seeds;
+        assert!(
+            matches!(&seeds[0], IdlSeed::Const(seed) if seed.value == serde_json::json!(b"counter"))
+        );
+        assert!(matches!(&seeds[1], IdlSeed::Account(seed) if seed.path == "authority"));
+
+        let increment = &idl.instructions[1];
+        assert_eq!(
+            increment.args,
+            [
+                field("amount", IdlType::U64),
+                field("memo", IdlType::Option(Box::new(IdlType::String))),
+            ]
+        );
+        let history = match &increment.accounts[2] {
+            IdlAccountItem::IdlAccounts(accounts) => accounts,
+            IdlAccountItem::IdlAccount(_) => panic!("history is not a group"),
+        };
+        assert_eq!(history.name, "history");
+        match &history.accounts[1] {
+            IdlAccountItem::IdlAccount(clock) => {
+                assert_eq!(clock.is_optional, Some(true));
+                assert_eq!(
+                    clock.address.as_deref(),
+                    Some("SysvarC1ock11111111111111111111111111111111")
+                );
+            }
+            IdlAccountItem::IdlAccounts(_) => panic!("clock is a group"),
+        }
+    }
+
+    #[test]
+    fn converts_counter_types() {
+        let idl = parse_idl(COUNTER_IDL).unwrap();
+
+        // Accounts and events are moved out of the types, and aliases are inlined.
+        let accounts: Vec<&str> = idl.accounts.iter().map(|def| def.name.as_str()).collect();
+        assert_eq!(accounts, ["Counter", "Log"]);
+        let types: Vec<&str> = idl.types.iter().map(|def| def.name.as_str()).collect();
+        assert_eq!(
+            types,
+            ["InitializeParams", "Mode", "Wrapper", "PairU64", "RingI644"]
+        );
+
+        let counter = &idl.accounts[0];
+        assert_eq!(
+            counter.discriminator,
+            Some(vec![255, 176, 4, 245, 188, 253, 124, 25])
+        );
+        assert_eq!(
+            counter.docs,
+            Some(vec!["Tracks a running total.".to_string()])
+        );
+        let fields = struct_fields(&idl, "Counter");
+        assert_eq!(fields[1].docs, Some(vec!["Current value.".to_string()]));
+        assert_eq!(fields[2].ty, IdlType::Defined("PairU64".to_string()));
+        assert_eq!(fields[3].ty, IdlType::Defined("RingI644".to_string()));
+        assert_eq!(
+            idl.accounts[1].serialization,
+            Some(IdlSerialization::Bytemuck)
+        );
+        assert!(matches!(
+            idl.accounts[1].repr,
+            Some(IdlRepr { packed: false, .. })
+        ));
+
+        assert_eq!(
+            struct_fields(&idl, "InitializeParams")[0],
+            field("start", IdlType::U64)
+        );
+        assert_eq!(
+            struct_fields(&idl, "PairU64"),
+            [field("low", IdlType::U64), field("high", IdlType::U64)]
+        );
+        let ring = struct_fields(&idl, "RingI644");
+        assert!(ring
+            .iter()
+            .any(|field| field.ty == IdlType::Array(Box::new(IdlType::I64), 4)));
+
+        let mode = idl.types.iter().find(|def| def.name == "Mode").unwrap();
+        match &mode.ty {
+            IdlTypeDefinitionTy::Enum { variants } => {
+                assert_eq!(variants[0].fields, None);
+                assert_eq!(
+                    variants[1].fields,
+                    Some(EnumFields::Named(vec![field("max", IdlType::U64)]))
+                );
+                assert_eq!(
+                    variants[2].fields,
+                    Some(EnumFields::Tuple(vec![IdlType::U32, IdlType::U32]))
+                );
+            }
+            IdlTypeDefinitionTy::Struct { .. } => panic!("Mode is a struct"),
+        }
+    }
+
+    #[test]
+    fn converts_counter_events_and_errors() {
+        let idl = parse_idl(COUNTER_IDL).unwrap();
+        let events = idl.events.unwrap();
+        assert_eq!(events[0].name, "Incremented");
+        assert_eq!(
+            events[0].discriminator,
+            Some(vec![92, 207, 119, 204, 71, 205, 108, 15])
+        );
+        assert_eq!(
+            events[0].fields,
+            [
+                IdlEventField {
+                    name: "counter".to_string(),
+                    docs: Some(vec!["Counter which was incremented.".to_string()]),
+                    ty: IdlType::PublicKey,
+                    index: false,
+                },
+                IdlEventField {
+                    name: "amount".to_string(),
+                    docs: None,
+                    ty: IdlType::U64,
+                    index: false,
+                },
+            ]
+        );
+
+        let errors = idl.errors.unwrap();
+        assert_eq!(errors[0].code, 6000);
+        assert_eq!(errors[0].msg.as_deref(), Some("Counter overflowed"));
+        assert_eq!(errors[1].msg, None);
+    }
+
+    #[test]
+    fn rejects_invalid_types() {
+        let mut converter = Converter {
+            typedefs: BTreeMap::new(),
+            instantiated: HashSet::new(),
+            instances: vec![],
+        };
+        let generics = Generics::new();
+        for ty in [
+            serde_json::json!({}),
+            serde_json::json!({"option": "u8", "vec": "u8"}),
+            serde_json::json!("u256"),
+            serde_json::json!({"array": ["u8"]}),
+        ] {
+            assert!(converter.convert_type(&ty, &generics).is_err(), "{}", ty);
+        }
+    }
+}
@@ -1,18 +1,34 @@
-use anchor_syn::idl::IdlInstruction;
-use heck::{ToPascalCase, ToSnakeCase};
+use crate::{
+    fn_ident, generate_discriminator_const, generate_docs, generate_fields, generate_leading_docs,
+    generate_serde_derives, generate_serde_field_attr, idl_instruction_discriminator,
+    ix_struct_ident, unique_field_idents_excluding, GenIdent, IdlInstruction, IdlType, SerdeOpts,
+};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
+/// Names used by the generated instruction functions, which args are renamed around.
+const RESERVED_ARG_NAMES: &[&str] = &["ctx", "accounts", "ix", "instruction_data"];
+
+/// Generates the identifiers of an instruction's args, renaming the ones which would collide.
+pub fn ix_arg_idents(ix: &IdlInstruction) -> Vec<GenIdent> {
+    unique_field_idents_excluding(
+        ix.args.iter().map(|arg| arg.name.as_str()),
+        RESERVED_ARG_NAMES,
+    )
+}
+
 /// Generates a single instruction handler.
 pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
-    let ix_name = format_ident!("{}", ix.name.to_snake_case());
-    let accounts_name = format_ident!("{}", ix.name.to_pascal_case());
+    let ix_name = fn_ident(&ix.name);
+    let accounts_name = ix_struct_ident(&ix.name);
+    let docs = generate_docs(ix.docs.as_deref());
 
     let args = ix
         .args
         .iter()
-        .map(|arg| {
-            let name = format_ident!("_{}", arg.name.to_snake_case());
+        .zip(ix_arg_idents(ix))
+        .map(|(arg, name)| {
+            let name = format_ident!("_{}", name.unraw());
             let type_name = crate::ty_to_rust_type(&arg.ty);
             let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
             quote! {
@@ -23,6 +39,7 @@ pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
 
     if cfg!(feature = "compat-program-result") {
         quote! {
+            #docs
             pub fn #ix_name(
                 _ctx: Context<#accounts_name>,
                 #(#args),*
@@ -32,6 +49,7 @@ pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
         }
     } else {
         quote! {
+            #docs
             pub fn #ix_name(
                 _ctx: Context<#accounts_name>,
                 #(#args),*
@@ -45,10 +63,10 @@ pub fn generate_ix_handler(ix: &IdlInstruction) -> TokenStream {
 /// Generates instruction context structs.
 pub fn generate_ix_structs(ixs: &[IdlInstruction]) -> TokenStream {
     let defs = ixs.iter().map(|ix| {
-        let accounts_name = format_ident!("{}", ix.name.to_pascal_case());
+        let accounts_name = ix_struct_ident(&ix.name);
 
         let (all_structs, all_fields) =
-            crate::generate_account_fields(&ix.name.to_pascal_case(), &ix.accounts);
+            crate::generate_account_fields(&accounts_name.to_string(), &ix.accounts);
 
         quote! {
             #all_structs
@@ -64,10 +82,159 @@ pub fn generate_ix_structs(ixs: &[IdlInstruction]) -> TokenStream {
     }
 }
 
+/// Generates the `DISCRIMINATOR` constants of the instruction structs from Anchor's
+/// `instruction` module.
+pub fn generate_ix_discriminators(ixs: &[IdlInstruction]) -> TokenStream {
+    let impls = ixs.iter().map(|ix| {
+        let ix_struct = ix_struct_ident(&ix.name);
+        let discriminator = generate_discriminator_const(&idl_instruction_discriminator(ix));
+        quote! {
+            impl instruction::#ix_struct {
+                #discriminator
+            }
+        }
+    });
+    quote! {
+        #(#impls)*
+    }
+}
+
+/// Generates the struct of an instruction's args, as serialized after its discriminator.
+///
+/// Unlike the structs of Anchor's `instruction` module, its fields are named as in the IDL.
+pub fn generate_ix_args_struct(ix: &IdlInstruction, serde: Option<SerdeOpts>) -> TokenStream {
+    let struct_name = ix_struct_ident(&ix.name);
+    let idl_docs = generate_leading_docs(ix.docs.as_deref());
+    let doc = format!(" Args of the `{}` instruction.", ix.name);
+    let fields = generate_fields(&ix.args, serde);
+    let discriminator = generate_discriminator_const(&idl_instruction_discriminator(ix));
+    let derive_serde = generate_serde_derives(serde);
+    quote! {
+        #idl_docs
+        #[doc = #doc]
+        #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+        #derive_serde
+        pub struct #struct_name {
+            #fields
+        }
+
+        impl #struct_name {
+            #discriminator
+        }
+    }
+}
+
+/// Generates the structs of the args of every instruction.
+pub fn generate_ix_args_structs(ixs: &[IdlInstruction], serde: Option<SerdeOpts>) -> TokenStream {
+    let streams = ixs.iter().map(|ix| generate_ix_args_struct(ix, serde));
+    quote! {
+        #(#streams)*
+    }
+}
+
+/// Generates the `DecodedInstruction` enum, holding the args of an instruction along with its
+/// accounts, and `InstructionType::decode_with_accounts` which returns it.
+pub fn generate_decoded_instruction(ixs: &[IdlInstruction], serde: Option<SerdeOpts>) -> TokenStream {
+    let remaining_attr =
+        generate_serde_field_attr(&IdlType::Vec(Box::new(IdlType::PublicKey)), serde);
+    let variants = ixs.iter().map(|ix| {
+        let ix_struct = ix_struct_ident(&ix.name);
+        quote! {
+            #ix_struct {
+                args: args::#ix_struct,
+                accounts: client::accounts::#ix_struct,
+                /// Accounts past the ones listed by the IDL, such as Anchor's remaining accounts.
+                #remaining_attr
+                remaining_accounts: Vec<Pubkey>,
+            }
+        }
+    });
+    let arms = ixs.iter().map(|ix| {
+        let ix_struct = ix_struct_ident(&ix.name);
+        quote! {
+            InstructionType::#ix_struct(args) => {
+                let (accounts, remaining_accounts) = anchor_gen::split_account_keys(
+                    keys,
+                    client::accounts::#ix_struct::ACCOUNTS_LEN,
+                )?;
+                DecodedInstruction::#ix_struct {
+                    args,
+                    accounts: client::accounts::#ix_struct::from_account_keys(accounts),
+                    remaining_accounts: remaining_accounts.to_vec(),
+                }
+            }
+        }
+    });
+    let derive_serde = generate_serde_derives(serde);
+    quote! {
+        /// An instruction decoded along with the keys of its accounts.
+        #[derive(Clone, Debug)]
+        #derive_serde
+        pub enum DecodedInstruction {
+            #(#variants),*
+        }
+
+        impl InstructionType {
+            /// Decodes the args of an instruction along with its accounts, from the keys of the
+            /// accounts in the order they were passed to the instruction.
+            pub fn decode_with_accounts(
+                data: &[u8],
+                keys: &[Pubkey],
+            ) -> std::result::Result<DecodedInstruction, anchor_gen::DecodeError> {
+                Ok(match <InstructionType as anchor_gen::Decode>::decode(data)? {
+                    #(#arms)*
+                })
+            }
+        }
+    }
+}
+
 /// Generates all instruction handlers.
 pub fn generate_ix_handlers(ixs: &[IdlInstruction]) -> TokenStream {
     let streams = ixs.iter().map(generate_ix_handler);
     quote! {
         #(#streams)*
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction() -> IdlInstruction {
+        serde_json::from_str(
+            r#"{"name": "swapExact", "accounts": [], "args": [
+                {"name": "amountIn", "type": "u64"},
+                {"name": "ctx", "type": "u64"},
+                {"name": "type", "type": "u8"}]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn args_struct_fields_are_named_as_in_the_idl() {
+        let generated = generate_ix_args_struct(&instruction(), None).to_string();
+        let expected = quote! {
+            pub struct SwapExact {
+                pub amount_in: u64,
+                pub ctx: u64,
+                pub r#type: u8
+            }
+        };
+        assert!(generated.contains(&expected.to_string()));
+        assert!(generated.contains("DISCRIMINATOR"));
+    }
+
+    #[test]
+    fn handler_args_are_renamed_around_reserved_names() {
+        let generated = generate_ix_handler(&instruction()).to_string();
+        let expected = quote! {
+            pub fn swap_exact(
+                _ctx: Context<SwapExact>,
+                _amount_in: u64,
+                _ctx_1: u64,
+                _type: u8
+            )
+        };
+        assert!(generated.contains(&expected.to_string()));
+    }
+}
@@ -0,0 +1,431 @@
+//! Chooses which structs are zero copy and `repr(packed)` from the IDL, for
+//! [Generator::with_auto_layout](crate::Generator::with_auto_layout).
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    get_field_list_size, get_variant_list_size, idl_account_discriminator, EnumFields, Idl,
+    IdlField, IdlSerialization, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy, StructOpts,
+};
+
+/// Accounts whose data is at least this large are made zero copy, since Borsh deserializes
+/// accounts onto the stack, of which a program only has 4KB.
+pub const ZERO_COPY_MIN_LEN: usize = 1024;
+
+/// Chooses the layout of the accounts and typedefs of an IDL.
+///
+/// Structs are zero copy if the IDL says they are serialized with bytemuck, or if they are
+/// accounts of at least [ZERO_COPY_MIN_LEN] bytes made only of primitives, arrays and other such
+/// structs. The typedefs used by a zero copy struct are zero copy too, so accounts are left as
+/// they are if one of those typedefs is also used by a Borsh-serialized type.
+///
+/// Zero copy structs are `repr(packed)` if the IDL's `repr` says so or, without a `repr`, if
+/// `repr(C)` would add padding, which `bytemuck::Pod` doesn't allow.
+pub fn auto_struct_opts(idl: &Idl) -> BTreeMap<String, StructOpts> {
+    let defs: Vec<IdlTypeDefinition> = idl.types.iter().chain(&idl.accounts).cloned().collect();
+    let defs = defs.as_slice();
+
+    let declared: Vec<&str> = defs
+        .iter()
+        .filter(|def| {
+            matches!(
+                def.serialization,
+                Some(IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe)
+            )
+        })
+        .map(|def| def.name.as_str())
+        .collect();
+    let mut large: Vec<&str> = idl
+        .accounts
+        .iter()
+        .filter(|def| def.serialization.is_none() && is_large_pod_account(defs, def))
+        .map(|def| def.name.as_str())
+        .collect();
+
+    // Large accounts are dropped until none of their typedefs is needed by a Borsh type.
+    let zero_copy = loop {
+        let zero_copy = reachable(defs, declared.iter().chain(&large).copied());
+        let borsh = borsh_reachable(idl, defs, &zero_copy);
+        let before = large.len();
+        large.retain(|name| reachable(defs, [*name]).is_disjoint(&borsh));
+        if large.len() == before {
+            break zero_copy;
+        }
+    };
+
+    zero_copy
+        .into_iter()
+        .filter_map(|name| {
+            let def = find_def(defs, name)?;
+            Some((
+                name.to_string(),
+                StructOpts {
+                    zero_copy: true,
+                    packed: is_packed(defs, def),
+                },
+            ))
+        })
+        .collect()
+}
+
+fn find_def<'a>(defs: &'a [IdlTypeDefinition], name: &str) -> Option<&'a IdlTypeDefinition> {
+    defs.iter().find(|def| def.name == name)
+}
+
+fn is_large_pod_account(defs: &[IdlTypeDefinition], def: &IdlTypeDefinition) -> bool {
+    let fields = match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => fields,
+        IdlTypeDefinitionTy::Enum { .. } => return false,
+    };
+    let len = match get_field_list_size(defs, fields).fixed() {
+        Some(size) => idl_account_discriminator(def).len() + size,
+        None => return false,
+    };
+    len >= ZERO_COPY_MIN_LEN && fields.iter().all(|field| is_pod(defs, &field.ty))
+}
+
+/// Returns true if the type can be zero copy, which excludes enums and types of variable size.
+///
+/// Booleans are allowed, as in Anchor's own zero copy accounts, although Anchor 0.27 and later
+/// only accept them in `zero_copy(unsafe)`.
+fn is_pod(defs: &[IdlTypeDefinition], ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Bool
+        | IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::F32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::F64
+        | IdlType::U128
+        | IdlType::I128
+        | IdlType::PublicKey => true,
+        IdlType::Array(inner, _) => is_pod(defs, inner),
+        IdlType::Defined(name) => match find_def(defs, name) {
+            Some(def) if def.serialization != Some(IdlSerialization::Borsh) => match &def.ty {
+                IdlTypeDefinitionTy::Struct { fields } => {
+                    fields.iter().all(|field| is_pod(defs, &field.ty))
+                }
+                IdlTypeDefinitionTy::Enum { .. } => false,
+            },
+            _ => false,
+        },
+        IdlType::Bytes | IdlType::String | IdlType::Option(_) | IdlType::Vec(_) => false,
+    }
+}
+
+/// Returns the names of the structs and every typedef they use, directly or not.
+fn reachable<'a>(
+    defs: &'a [IdlTypeDefinition],
+    names: impl IntoIterator<Item = &'a str>,
+) -> HashSet<&'a str> {
+    let mut found = HashSet::new();
+    let mut pending: Vec<&str> = names.into_iter().collect();
+    while let Some(name) = pending.pop() {
+        let def = match find_def(defs, name) {
+            Some(def) if found.insert(def.name.as_str()) => def,
+            _ => continue,
+        };
+        for ty in def_types(def) {
+            defined_names(ty, &mut pending);
+        }
+    }
+    found
+}
+
+/// Returns the typedefs used by Borsh-serialized types: instruction arguments, events and the
+/// accounts and typedefs which are not zero copy.
+fn borsh_reachable<'a>(
+    idl: &'a Idl,
+    defs: &'a [IdlTypeDefinition],
+    zero_copy: &HashSet<&'a str>,
+) -> HashSet<&'a str> {
+    let mut roots = vec![];
+    let args = idl
+        .instructions
+        .iter()
+        .flat_map(|ix| &ix.args)
+        .map(|arg| &arg.ty);
+    let events = idl.events.iter().flatten().flat_map(|event| &event.fields);
+    for ty in args.chain(events.map(|field| &field.ty)) {
+        defined_names(ty, &mut roots);
+    }
+    for def in defs
+        .iter()
+        .filter(|def| !zero_copy.contains(def.name.as_str()))
+    {
+        for ty in def_types(def) {
+            defined_names(ty, &mut roots);
+        }
+    }
+    reachable(defs, roots)
+}
+
+/// Returns the types of the fields of a struct or of the variants of an enum.
+fn def_types(def: &IdlTypeDefinition) -> Vec<&IdlType> {
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => fields.iter().map(|field| &field.ty).collect(),
+        IdlTypeDefinitionTy::Enum { variants } => variants
+            .iter()
+            .flat_map(|variant| match &variant.fields {
+                Some(EnumFields::Named(fields)) => fields.iter().map(|field| &field.ty).collect(),
+                Some(EnumFields::Tuple(types)) => types.iter().collect(),
+                None => vec![],
+            })
+            .collect(),
+    }
+}
+
+fn defined_names<'a>(ty: &'a IdlType, names: &mut Vec<&'a str>) {
+    match ty {
+        IdlType::Defined(name) => names.push(name),
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            defined_names(inner, names)
+        }
+        _ => {}
+    }
+}
+
+fn is_packed(defs: &[IdlTypeDefinition], def: &IdlTypeDefinition) -> bool {
+    match &def.repr {
+        Some(repr) => repr.packed,
+        // `u128` is aligned to 16 bytes, as it is on x86-64, so that structs have no padding on
+        // any target.
+        None => {
+            let rules = LayoutRules {
+                packed: &|def| Some(is_packed(defs, def)),
+                int128_align: 16,
+            };
+            c_layout(defs, def, rules).is_some_and(|layout| layout.padded)
+        }
+    }
+}
+
+/// Returns the offsets of the fields of a `repr(C)` zero copy struct, as laid out by Rust.
+///
+/// Returns `None` if they can't be known from the IDL, which is when a field is a typedef that
+/// isn't zero copy or when they depend on the alignment of `u128`, which is 16 bytes on
+/// x86-64 since Rust 1.77 but 8 on older versions and on BPF.
+pub fn c_field_offsets(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    fields: &[IdlField],
+) -> Option<Vec<usize>> {
+    let [(_, offsets_8), (_, offsets_16)] = layouts(defs, struct_opts, fields)?;
+    (offsets_8 == offsets_16).then_some(offsets_16)
+}
+
+/// Returns the size of a `repr(C)` zero copy struct, padding included, or `None` if it can't
+/// be known from the IDL, as for [c_field_offsets].
+pub fn c_struct_size(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    fields: &[IdlField],
+) -> Option<usize> {
+    let [(size_8, _), (size_16, _)] = layouts(defs, struct_opts, fields)?;
+    (size_8 == size_16).then_some(size_16)
+}
+
+/// Returns the size and field offsets of a `repr(C)` struct with `u128` aligned to 8 bytes,
+/// then to 16 bytes.
+fn layouts(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    fields: &[IdlField],
+) -> Option<[(usize, Vec<usize>); 2]> {
+    let packed = |def: &IdlTypeDefinition| {
+        let opts = struct_opts.get(&def.name)?;
+        opts.zero_copy.then_some(opts.packed)
+    };
+    let layout = |int128_align| {
+        let rules = LayoutRules {
+            packed: &packed,
+            int128_align,
+        };
+        fields_layout(defs, fields, rules).map(|(layout, offsets)| (layout.size, offsets))
+    };
+    Some([layout(8)?, layout(16)?])
+}
+
+/// How the types are laid out.
+#[derive(Clone, Copy)]
+struct LayoutRules<'a> {
+    /// Whether a typedef is `repr(packed)`, or `None` if its layout isn't known.
+    packed: &'a dyn Fn(&IdlTypeDefinition) -> Option<bool>,
+    /// Alignment of `u128` and `i128`.
+    int128_align: usize,
+}
+
+/// Layout of a zero copy type in memory.
+struct Layout {
+    size: usize,
+    align: usize,
+    /// Whether `repr(C)` adds padding between the fields or after the last one.
+    padded: bool,
+}
+
+fn c_layout(
+    defs: &[IdlTypeDefinition],
+    def: &IdlTypeDefinition,
+    rules: LayoutRules,
+) -> Option<Layout> {
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => {
+            fields_layout(defs, fields, rules).map(|(layout, _)| layout)
+        }
+        IdlTypeDefinitionTy::Enum { .. } => None,
+    }
+}
+
+/// Returns the `repr(C)` layout of a list of fields, along with the offset of each field.
+fn fields_layout(
+    defs: &[IdlTypeDefinition],
+    fields: &[IdlField],
+    rules: LayoutRules,
+) -> Option<(Layout, Vec<usize>)> {
+    let mut layout = Layout {
+        size: 0,
+        align: 1,
+        padded: false,
+    };
+    let mut offsets = vec![];
+    for field in fields {
+        let (size, align) = type_layout(defs, &field.ty, rules)?;
+        layout.pad_to(align);
+        offsets.push(layout.size);
+        layout.size += size;
+        layout.align = layout.align.max(align);
+    }
+    layout.pad_to(layout.align);
+    Some((layout, offsets))
+}
+
+impl Layout {
+    /// Adds the padding needed for the size to be a multiple of `align`.
+    fn pad_to(&mut self, align: usize) {
+        let padding = (align - self.size % align) % align;
+        self.size += padding;
+        self.padded |= padding > 0;
+    }
+}
+
+/// Returns the size and alignment of a zero copy type, or `None` for types which can't be zero
+/// copy.
+fn type_layout(
+    defs: &[IdlTypeDefinition],
+    ty: &IdlType,
+    rules: LayoutRules,
+) -> Option<(usize, usize)> {
+    match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 => Some((1, 1)),
+        IdlType::U16 | IdlType::I16 => Some((2, 2)),
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => Some((4, 4)),
+        IdlType::U64 | IdlType::I64 | IdlType::F64 => Some((8, 8)),
+        IdlType::U128 | IdlType::I128 => Some((16, rules.int128_align)),
+        IdlType::PublicKey => Some((32, 1)),
+        IdlType::Array(inner, len) => {
+            let (size, align) = type_layout(defs, inner, rules)?;
+            Some((size * len, align))
+        }
+        // Packed structs have no padding, so their size is the one of their Borsh serialization.
+        IdlType::Defined(name) => {
+            let def = find_def(defs, name)?;
+            if (rules.packed)(def)? {
+                let size = match &def.ty {
+                    IdlTypeDefinitionTy::Struct { fields } => get_field_list_size(defs, fields),
+                    IdlTypeDefinitionTy::Enum { variants } => get_variant_list_size(defs, variants),
+                };
+                Some((size.fixed()?, 1))
+            } else {
+                let layout = c_layout(defs, def, rules)?;
+                Some((layout.size, layout.align))
+            }
+        }
+        IdlType::Bytes | IdlType::String | IdlType::Option(_) | IdlType::Vec(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(json: &str) -> Vec<IdlField> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn c_field_offsets_are_padded() {
+        let defs: Vec<IdlTypeDefinition> = serde_json::from_str(
+            r#"[{"name": "Inner", "type": {"kind": "struct", "fields": [
+                {"name": "a", "type": "u8"}, {"name": "b", "type": "u32"}
+            ]}}]"#,
+        )
+        .unwrap();
+        let zero_copy = |packed| {
+            let opts = StructOpts {
+                zero_copy: true,
+                packed,
+            };
+            BTreeMap::from([("Inner".to_string(), opts)])
+        };
+
+        let foo = fields(
+            r#"[{"name": "a", "type": "u8"}, {"name": "b", "type": "u64"},
+                {"name": "c", "type": "publicKey"}]"#,
+        );
+        assert_eq!(
+            c_field_offsets(&defs, &BTreeMap::new(), &foo),
+            Some(vec![0, 8, 16])
+        );
+
+        let nested = fields(
+            r#"[{"name": "a", "type": "u8"}, {"name": "inner", "type": {"defined": "Inner"}},
+                {"name": "b", "type": "u8"}]"#,
+        );
+        assert_eq!(
+            c_field_offsets(&defs, &zero_copy(false), &nested),
+            Some(vec![0, 4, 12])
+        );
+        assert_eq!(
+            c_field_offsets(&defs, &zero_copy(true), &nested),
+            Some(vec![0, 1, 6])
+        );
+        // Borsh structs have the layout Rust chooses.
+        assert_eq!(c_field_offsets(&defs, &BTreeMap::new(), &nested), None);
+    }
+
+    #[test]
+    fn c_struct_size_depending_on_u128_alignment_is_unknown() {
+        // 32 bytes where `u128` is aligned to 16 bytes, but 24 on BPF.
+        let padded = fields(r#"[{"name": "a", "type": "u128"}, {"name": "b", "type": "u64"}]"#);
+        assert_eq!(c_struct_size(&[], &BTreeMap::new(), &padded), None);
+
+        let aligned = fields(
+            r#"[{"name": "a", "type": "u128"}, {"name": "b", "type": "u64"},
+                {"name": "c", "type": "u64"}]"#,
+        );
+        assert_eq!(c_struct_size(&[], &BTreeMap::new(), &aligned), Some(32));
+
+        let foo = fields(
+            r#"[{"name": "a", "type": "u8"}, {"name": "b", "type": "u64"},
+                {"name": "c", "type": "publicKey"}]"#,
+        );
+        assert_eq!(c_struct_size(&[], &BTreeMap::new(), &foo), Some(48));
+    }
+
+    #[test]
+    fn c_field_offsets_depending_on_u128_alignment_are_unknown() {
+        let padded = fields(r#"[{"name": "a", "type": "u64"}, {"name": "b", "type": "u128"}]"#);
+        assert_eq!(c_field_offsets(&[], &BTreeMap::new(), &padded), None);
+
+        let aligned = fields(r#"[{"name": "a", "type": "u128"}, {"name": "b", "type": "u64"}]"#);
+        assert_eq!(
+            c_field_offsets(&[], &BTreeMap::new(), &aligned),
+            Some(vec![0, 16])
+        );
+    }
+}
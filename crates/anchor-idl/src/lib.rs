@@ -1,25 +1,57 @@
 //! Generates Rust code from an Anchor IDL.
 
-pub use anchor_syn::idl::*;
-
 mod account;
+mod builder;
+mod client;
+mod constants;
+mod cpi;
+mod discriminator;
+mod docs;
+mod dynamic;
+mod errors;
+mod events;
+mod filter;
+mod ident;
+mod idl;
 mod instruction;
+mod layout;
+mod pda;
 mod program;
+mod serde_attrs;
 mod state;
 mod typedef;
+mod validate;
 mod decode;
 
 pub use account::*;
+pub use builder::*;
+pub use client::*;
+pub use constants::*;
+pub use cpi::*;
+pub use discriminator::*;
+pub use docs::*;
+pub use dynamic::*;
+pub use errors::*;
+pub use events::*;
+pub use filter::*;
+pub use ident::*;
+pub use idl::*;
 pub use instruction::*;
+pub use layout::*;
+pub use pda::*;
 pub use program::*;
+pub use serde_attrs::*;
 pub use state::*;
 pub use typedef::*;
+pub use validate::*;
 pub use decode::*;
 
 /// Version of anchor-idl.
 pub const GEN_VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 
 /// Converts an [IdlType] to a [String] of the Rust representation.
+///
+/// Defined types are referred to by name, or by path once replaced through [Generator::with_type_map].
 pub fn ty_to_rust_type(ty: &IdlType) -> String {
     match ty {
         IdlType::Bool => "bool".to_string(),
@@ -41,6 +73,6 @@ pub fn ty_to_rust_type(ty: &IdlType) -> String {
         IdlType::Option(inner) => format!("Option<{}>", ty_to_rust_type(inner)),
         IdlType::Vec(inner) => format!("Vec<{}>", ty_to_rust_type(inner)),
         IdlType::Array(ty, size) => format!("[{}; {}]", ty_to_rust_type(ty), size),
-        IdlType::Defined(name) => name.to_string(),
+        IdlType::Defined(name) => type_path(name),
     }
 }
@@ -0,0 +1,630 @@
+//! Derives the addresses of PDA accounts from the seeds declared in the IDL.
+
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use heck::ToSnakeCase;
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    account_field_idents, field_ident, ix_arg_idents, unique_field_idents, IdlAccountItem,
+    IdlInstruction, IdlPda, IdlSeed, IdlSeedAccount, IdlSeedArg, IdlSeedConst, IdlType,
+    IdlTypeDefinition, IdlTypeDefinitionTy,
+};
+
+/// A seed of a PDA, resolved against its instruction and the types of the IDL.
+#[derive(Clone, Debug, PartialEq)]
+enum Seed {
+    Const(Vec<u8>),
+    /// An instruction arg, or a field of one, named by its path in the IDL.
+    Arg {
+        path: Vec<String>,
+        ty: IdlType,
+    },
+    /// The address of another account of the instruction, named by the IDL names of the
+    /// account and the groups it's nested in.
+    Account {
+        path: Vec<String>,
+    },
+    /// A field of another account's data, which can only be passed in.
+    AccountField {
+        path: Vec<String>,
+        ty: IdlType,
+    },
+}
+
+/// An account of an instruction whose address can be derived from its seeds.
+#[derive(Clone, Debug)]
+struct Pda<'a> {
+    ix: &'a IdlInstruction,
+    /// IDL names of the account and the groups it's nested in.
+    path: Vec<String>,
+    seeds: Vec<Seed>,
+    program_id: Option<Vec<u8>>,
+}
+
+impl Pda<'_> {
+    fn name(&self) -> &str {
+        self.path.last().unwrap()
+    }
+
+    /// The parameters of the `find_*_address` function, one for each seed that isn't constant.
+    fn params(&self) -> Vec<(Ident, &IdlType)> {
+        let inputs: Vec<(String, &IdlType)> = self
+            .seeds
+            .iter()
+            .filter_map(|seed| match seed {
+                Seed::Const(_) => None,
+                Seed::Arg { path, ty } => Some((path.last().unwrap().clone(), ty)),
+                Seed::Account { path } => Some((path.last().unwrap().clone(), &IdlType::PublicKey)),
+                Seed::AccountField { path, ty } => Some((path.join("_"), ty)),
+            })
+            .collect();
+        let names = unique_field_idents(inputs.iter().map(|(name, _)| name.as_str()));
+        names
+            .into_iter()
+            .zip(inputs)
+            .map(|(name, (_, ty))| (name.ident, ty))
+            .collect()
+    }
+}
+
+/// The PDA accounts of a program's instructions, and the functions deriving their addresses.
+///
+/// Accounts with the same name share a `find_<account>_address` function if they have the same
+/// seeds, otherwise each is named after its instruction, as in `find_<ix>_<account>_address`.
+pub struct Pdas<'a> {
+    pdas: Vec<(Pda<'a>, Ident)>,
+}
+
+impl<'a> Pdas<'a> {
+    /// Collects the PDAs of `ixs`, leaving out the ones with seeds that can't be derived
+    /// from the types in `defs`.
+    pub fn new(ixs: &'a [IdlInstruction], defs: &[IdlTypeDefinition]) -> Self {
+        let pdas: Vec<Pda<'a>> = ixs
+            .iter()
+            .flat_map(|ix| {
+                flatten_accounts(&ix.accounts, &[])
+                    .into_iter()
+                    .filter_map(move |(path, pda)| resolve_pda(ix, defs, path, pda?))
+            })
+            .collect();
+
+        let mut by_name: BTreeMap<String, Vec<&Pda>> = BTreeMap::new();
+        for pda in &pdas {
+            by_name
+                .entry(pda.name().to_snake_case())
+                .or_default()
+                .push(pda);
+        }
+        let pdas = pdas
+            .iter()
+            .map(|pda| {
+                let name = field_ident(pda.name()).unraw();
+                let shared = by_name[&pda.name().to_snake_case()]
+                    .iter()
+                    .all(|other| other.seeds == pda.seeds && other.program_id == pda.program_id);
+                let fn_name = if shared {
+                    format_ident!("find_{}_address", name)
+                } else {
+                    let ix_name = field_ident(&pda.ix.name).unraw();
+                    format_ident!("find_{}_{}_address", ix_name, name)
+                };
+                (pda.clone(), fn_name)
+            })
+            .collect();
+        Self { pdas }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pdas.is_empty()
+    }
+
+    /// Generates the `find_*_address` functions.
+    pub fn generate_helpers(&self) -> TokenStream {
+        let mut generated = HashSet::new();
+        let helpers = self
+            .pdas
+            .iter()
+            .filter(|(_, fn_name)| generated.insert(fn_name.to_string()))
+            .map(|(pda, fn_name)| {
+                let params = pda.params();
+                let mut param_names = params.iter().map(|(name, _)| name);
+                let seeds = pda.seeds.iter().map(|seed| match seed {
+                    Seed::Const(bytes) => {
+                        let bytes = Literal::byte_string(bytes);
+                        quote! { #bytes.as_ref() }
+                    }
+                    Seed::Arg { ty, .. } | Seed::AccountField { ty, .. } => {
+                        seed_bytes(param_names.next().unwrap(), ty)
+                    }
+                    Seed::Account { .. } => {
+                        seed_bytes(param_names.next().unwrap(), &IdlType::PublicKey)
+                    }
+                });
+                let param_list = params.iter().map(|(name, ty)| {
+                    let ty = param_type(ty);
+                    quote! { #name: #ty }
+                });
+                let program_id = match &pda.program_id {
+                    Some(bytes) => quote! { &Pubkey::new_from_array([#(#bytes),*]) },
+                    None => quote! { &ID },
+                };
+                let doc = format!(
+                    " Finds the address and bump of the `{}` account.",
+                    pda.name()
+                );
+                quote! {
+                    #[doc = #doc]
+                    pub fn #fn_name(#(#param_list),*) -> (Pubkey, u8) {
+                        Pubkey::find_program_address(&[#(#seeds),*], #program_id)
+                    }
+                }
+            });
+        quote! {
+            #(#helpers)*
+        }
+    }
+
+    /// Paths of the accounts of `ix` which its client builder derives when they are `None`,
+    /// joined with `.`.
+    ///
+    /// An account is derived if its seeds only refer to the instruction's args and to accounts
+    /// which are either passed in or derived before it.
+    pub fn derived_accounts(&self, ix: &IdlInstruction) -> Vec<String> {
+        self.derived(ix)
+            .into_iter()
+            .map(|(pda, _)| pda.path.join("."))
+            .collect()
+    }
+
+    /// Generates the statements of a client builder which derive the accounts listed by
+    /// [Self::derived_accounts], shadowing `accounts` with the resolved accounts.
+    pub fn generate_resolution(&self, ix: &IdlInstruction) -> TokenStream {
+        let derived = self.derived(ix);
+        if derived.is_empty() {
+            return quote! {};
+        }
+        let derived_paths: Vec<&Vec<String>> = derived.iter().map(|(pda, _)| &pda.path).collect();
+        let statements = derived.iter().map(|(pda, fn_name)| {
+            let field = account_expr(ix, &pda.path).unwrap();
+            let args = pda.seeds.iter().filter_map(|seed| match seed {
+                Seed::Const(_) => None,
+                Seed::Arg { path, ty } => {
+                    let arg = arg_expr(ix, path).unwrap();
+                    Some(if is_passed_by_value(ty) {
+                        quote! { #arg }
+                    } else {
+                        quote! { &#arg }
+                    })
+                }
+                Seed::Account { path } => {
+                    let account = account_expr(ix, path).unwrap();
+                    Some(if derived_paths.contains(&path) {
+                        quote! { #account.as_ref().unwrap() }
+                    } else {
+                        quote! { &#account }
+                    })
+                }
+                Seed::AccountField { .. } => unreachable!(),
+            });
+            quote! {
+                if #field.is_none() {
+                    #field = Some(pda::#fn_name(#(#args),*).0);
+                }
+            }
+        });
+        quote! {
+            let mut accounts = *accounts;
+            #(#statements)*
+            let accounts = &accounts;
+        }
+    }
+
+    fn derived(&self, ix: &IdlInstruction) -> Vec<&(Pda<'a>, Ident)> {
+        let ix_pdas: Vec<&(Pda, Ident)> = self
+            .pdas
+            .iter()
+            .filter(|(pda, _)| pda.ix.name == ix.name)
+            .collect();
+        let mut derived: Vec<&(Pda, Ident)> = vec![];
+        for (i, entry) in ix_pdas.iter().enumerate() {
+            let (pda, _) = entry;
+            let resolvable = pda.seeds.iter().all(|seed| match seed {
+                Seed::Const(_) => true,
+                Seed::Arg { path, .. } => arg_expr(ix, path).is_some(),
+                Seed::Account { path } => {
+                    // PDAs after this one may still become derived, so can't be referred to.
+                    *path != pda.path
+                        && account_expr(ix, path).is_some()
+                        && !ix_pdas[i + 1..]
+                            .iter()
+                            .any(|(later, _)| later.path == *path)
+                }
+                Seed::AccountField { .. } => false,
+            });
+            if resolvable {
+                derived.push(entry);
+            }
+        }
+        derived
+    }
+}
+
+/// Lists the accounts of an instruction, depth first, with the IDL names of the groups
+/// they are nested in.
+fn flatten_accounts<'a>(
+    accounts: &'a [IdlAccountItem],
+    prefix: &[String],
+) -> Vec<(Vec<String>, Option<&'a IdlPda>)> {
+    accounts
+        .iter()
+        .flat_map(|item| {
+            let mut path = prefix.to_vec();
+            match item {
+                IdlAccountItem::IdlAccount(account) => {
+                    path.push(account.name.clone());
+                    vec![(path, account.pda.as_ref())]
+                }
+                IdlAccountItem::IdlAccounts(group) => {
+                    path.push(group.name.clone());
+                    flatten_accounts(&group.accounts, &path)
+                }
+            }
+        })
+        .collect()
+}
+
+fn resolve_pda<'a>(
+    ix: &'a IdlInstruction,
+    defs: &[IdlTypeDefinition],
+    path: Vec<String>,
+    pda: &IdlPda,
+) -> Option<Pda<'a>> {
+    let seeds = pda
+        .seeds
+        .iter()
+        .map(|seed| resolve_seed(ix, defs, seed))
+        .collect::<Option<Vec<_>>>()?;
+    let program_id = match &pda.program_id {
+        None => None,
+        Some(IdlSeed::Const(seed)) => Some(const_seed_bytes(seed).filter(|b| b.len() == 32)?),
+        Some(_) => return None,
+    };
+    Some(Pda {
+        ix,
+        path,
+        seeds,
+        program_id,
+    })
+}
+
+fn resolve_seed(ix: &IdlInstruction, defs: &[IdlTypeDefinition], seed: &IdlSeed) -> Option<Seed> {
+    let seed = match seed {
+        IdlSeed::Const(seed) => Seed::Const(const_seed_bytes(seed)?),
+        IdlSeed::Arg(IdlSeedArg { ty, path }) => {
+            let path: Vec<String> = path.split('.').map(str::to_string).collect();
+            let arg = ix
+                .args
+                .iter()
+                .find(|arg| arg.name.to_snake_case() == path[0].to_snake_case())?;
+            let ty = field_type(defs, &arg.ty, &path[1..]).or_else(|| ty.clone())?;
+            Seed::Arg { path, ty }
+        }
+        IdlSeed::Account(IdlSeedAccount { ty, account, path }) => {
+            let segments: Vec<&str> = path.split('.').collect();
+            if let Some(path) = find_account(&ix.accounts, &segments) {
+                Seed::Account { path }
+            } else {
+                // The seed is a field of the account's data.
+                let def = defs.iter().find(|def| Some(&def.name) == account.as_ref());
+                let ty = def
+                    .and_then(|def| {
+                        field_type(defs, &IdlType::Defined(def.name.clone()), &segments[1..])
+                    })
+                    .or_else(|| ty.clone())?;
+                Seed::AccountField {
+                    path: segments.iter().map(|s| s.to_string()).collect(),
+                    ty,
+                }
+            }
+        }
+    };
+    if let Seed::Arg { ty, .. } | Seed::AccountField { ty, .. } = &seed {
+        param_type(ty)?;
+    }
+    Some(seed)
+}
+
+/// Finds the account at `segments`, either through the groups it's nested in or by its name alone.
+fn find_account(accounts: &[IdlAccountItem], segments: &[&str]) -> Option<Vec<String>> {
+    let flattened = flatten_accounts(accounts, &[]);
+    let matches = |path: &[String], segments: &[&str]| {
+        path.len() == segments.len()
+            && path
+                .iter()
+                .zip(segments)
+                .all(|(name, segment)| name.to_snake_case() == segment.to_snake_case())
+    };
+    if let Some((path, _)) = flattened.iter().find(|(path, _)| matches(path, segments)) {
+        return Some(path.clone());
+    }
+    match segments {
+        [name] => {
+            let mut found = flattened
+                .iter()
+                .filter(|(path, _)| matches(&path[path.len() - 1..], &[name]));
+            match (found.next(), found.next()) {
+                (Some((path, _)), None) => Some(path.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the type of the field at `path` within a value of type `ty`.
+fn field_type(
+    defs: &[IdlTypeDefinition],
+    ty: &IdlType,
+    path: &[impl AsRef<str>],
+) -> Option<IdlType> {
+    let (first, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return Some(ty.clone()),
+    };
+    let fields = match ty {
+        IdlType::Defined(name) => match &defs.iter().find(|def| def.name == *name)?.ty {
+            IdlTypeDefinitionTy::Struct { fields } => fields,
+            IdlTypeDefinitionTy::Enum { .. } => return None,
+        },
+        _ => return None,
+    };
+    let field = fields
+        .iter()
+        .find(|field| field.name.to_snake_case() == first.as_ref().to_snake_case())?;
+    field_type(defs, &field.ty, rest)
+}
+
+/// Returns the bytes of a constant seed, which Anchor writes as an array of bytes, or in legacy
+/// IDLs as a value of the seed's type.
+fn const_seed_bytes(seed: &IdlSeedConst) -> Option<Vec<u8>> {
+    match (&seed.value, &seed.ty) {
+        (JsonValue::Array(values), _) => values
+            .iter()
+            .map(|value| value.as_u64().and_then(|b| u8::try_from(b).ok()))
+            .collect(),
+        (JsonValue::String(value), Some(IdlType::PublicKey)) => {
+            Some(Pubkey::from_str(value).ok()?.to_bytes().to_vec())
+        }
+        (JsonValue::String(value), _) => Some(value.as_bytes().to_vec()),
+        (JsonValue::Number(value), Some(ty)) => Some(match ty {
+            IdlType::U8 => u8::try_from(value.as_u64()?).ok()?.to_le_bytes().to_vec(),
+            IdlType::U16 => u16::try_from(value.as_u64()?).ok()?.to_le_bytes().to_vec(),
+            IdlType::U32 => u32::try_from(value.as_u64()?).ok()?.to_le_bytes().to_vec(),
+            IdlType::U64 => value.as_u64()?.to_le_bytes().to_vec(),
+            IdlType::I8 => i8::try_from(value.as_i64()?).ok()?.to_le_bytes().to_vec(),
+            IdlType::I16 => i16::try_from(value.as_i64()?).ok()?.to_le_bytes().to_vec(),
+            IdlType::I32 => i32::try_from(value.as_i64()?).ok()?.to_le_bytes().to_vec(),
+            IdlType::I64 => value.as_i64()?.to_le_bytes().to_vec(),
+            _ => return None,
+        }),
+        _ => None,
+    }
+}
+
+/// The type of a `find_*_address` parameter for a seed of type `ty`.
+fn param_type(ty: &IdlType) -> Option<TokenStream> {
+    Some(match ty {
+        IdlType::PublicKey => quote! { &Pubkey },
+        IdlType::String => quote! { &str },
+        IdlType::Bytes => quote! { &[u8] },
+        IdlType::Array(inner, len) if **inner == IdlType::U8 => {
+            let len = Literal::usize_unsuffixed(*len);
+            quote! { &[u8; #len] }
+        }
+        ty if is_passed_by_value(ty) => crate::ty_to_rust_type(ty).parse().unwrap(),
+        _ => return None,
+    })
+}
+
+fn is_passed_by_value(ty: &IdlType) -> bool {
+    matches!(
+        ty,
+        IdlType::Bool
+            | IdlType::U8
+            | IdlType::I8
+            | IdlType::U16
+            | IdlType::I16
+            | IdlType::U32
+            | IdlType::I32
+            | IdlType::U64
+            | IdlType::I64
+            | IdlType::U128
+            | IdlType::I128
+    )
+}
+
+/// The bytes of the seed passed as the parameter `name` of type `ty`.
+fn seed_bytes(name: &Ident, ty: &IdlType) -> TokenStream {
+    match ty {
+        IdlType::Bool => quote! { &[#name as u8] },
+        IdlType::String => quote! { #name.as_bytes() },
+        IdlType::Bytes => quote! { #name },
+        IdlType::PublicKey | IdlType::Array(..) => quote! { #name.as_ref() },
+        _ => quote! { #name.to_le_bytes().as_ref() },
+    }
+}
+
+/// The expression of the account at `path` in a client builder.
+fn account_expr(ix: &IdlInstruction, path: &[String]) -> Option<TokenStream> {
+    let mut accounts = &ix.accounts;
+    let mut idents = vec![];
+    for (i, name) in path.iter().enumerate() {
+        let index = accounts.iter().position(|item| match item {
+            IdlAccountItem::IdlAccount(account) => account.name == *name,
+            IdlAccountItem::IdlAccounts(group) => group.name == *name,
+        })?;
+        idents.push(account_field_idents(accounts).swap_remove(index));
+        match &accounts[index] {
+            IdlAccountItem::IdlAccounts(group) => accounts = &group.accounts,
+            IdlAccountItem::IdlAccount(_) if i + 1 == path.len() => {}
+            IdlAccountItem::IdlAccount(_) => return None,
+        }
+    }
+    Some(quote! { accounts #(.#idents)* })
+}
+
+/// The expression of the arg, or field of an arg, at `path` in a client builder.
+fn arg_expr(ix: &IdlInstruction, path: &[String]) -> Option<TokenStream> {
+    let index = ix
+        .args
+        .iter()
+        .position(|arg| arg.name.to_snake_case() == path[0].to_snake_case())?;
+    let arg = ix_arg_idents(ix).swap_remove(index);
+    let fields = path[1..].iter().map(|field| field_ident(field));
+    Some(quote! { #arg #(.#fields)* })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_client_accounts_struct;
+
+    fn account(name: &str, seeds: &str) -> String {
+        format!(
+            r#"{{"name": "{}", "isMut": true, "isSigner": false, "pda": {{"seeds": [{}]}}}}"#,
+            name, seeds
+        )
+    }
+
+    /// An instruction whose accounts are listed in an order that exercises the derivation:
+    /// `vault` and `log` depend on PDAs derived before them, `early` on one listed after it and
+    /// `position` on the data of `pool`.
+    fn instruction() -> IdlInstruction {
+        let accounts = [
+            account(
+                "early",
+                r#"{"kind": "account", "type": "publicKey", "path": "late"}"#,
+            ),
+            account(
+                "pool",
+                r#"{"kind": "const", "type": "string", "value": "pool"},
+                   {"kind": "account", "type": "publicKey", "path": "authority"}"#,
+            ),
+            account(
+                "vault",
+                r#"{"kind": "const", "type": "string", "value": "vault"},
+                   {"kind": "account", "type": "publicKey", "path": "pool"}"#,
+            ),
+            r#"{"name": "authority", "isMut": false, "isSigner": true}"#.to_string(),
+            format!(
+                r#"{{"name": "extras", "accounts": [{}]}}"#,
+                account(
+                    "log",
+                    r#"{"kind": "account", "type": "publicKey", "path": "vault"},
+                       {"kind": "arg", "type": "u64", "path": "amount"}"#,
+                )
+            ),
+            account(
+                "position",
+                r#"{"kind": "account", "type": "publicKey", "account": "Pool", "path": "pool.owner"}"#,
+            ),
+            account(
+                "late",
+                r#"{"kind": "const", "type": "string", "value": "late"}"#,
+            ),
+        ];
+        serde_json::from_str(&format!(
+            r#"{{"name": "open", "accounts": [{}], "args": [{{"name": "amount", "type": "u64"}}]}}"#,
+            accounts.join(",")
+        ))
+        .unwrap()
+    }
+
+    fn defs() -> Vec<IdlTypeDefinition> {
+        serde_json::from_str(
+            r#"[{"name": "Pool", "type": {"kind": "struct", "fields": [
+                {"name": "owner", "type": "publicKey"}
+            ]}}]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn derives_accounts_whose_seeds_are_known() {
+        let ixs = [instruction()];
+        let pdas = Pdas::new(&ixs, &defs());
+        assert_eq!(
+            pdas.derived_accounts(&ixs[0]),
+            ["pool", "vault", "extras.log", "late"]
+        );
+
+        let helpers = pdas.generate_helpers().to_string();
+        let position = quote! {
+            pub fn find_position_address(pool_owner: &Pubkey) -> (Pubkey, u8)
+        };
+        assert!(helpers.contains(&position.to_string()));
+    }
+
+    #[test]
+    fn derives_accounts_in_order() {
+        let ixs = [instruction()];
+        let pdas = Pdas::new(&ixs, &defs());
+        let expected = quote! {
+            let mut accounts = *accounts;
+            if accounts.pool.is_none() {
+                accounts.pool = Some(pda::find_pool_address(&accounts.authority).0);
+            }
+            if accounts.vault.is_none() {
+                accounts.vault = Some(pda::find_vault_address(accounts.pool.as_ref().unwrap()).0);
+            }
+            if accounts.extras.log.is_none() {
+                accounts.extras.log =
+                    Some(pda::find_log_address(accounts.vault.as_ref().unwrap(), amount).0);
+            }
+            if accounts.late.is_none() {
+                accounts.late = Some(pda::find_late_address().0);
+            }
+            let accounts = &accounts;
+        };
+        assert_eq!(
+            pdas.generate_resolution(&ixs[0]).to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn derived_accounts_are_optional() {
+        let ixs = [instruction()];
+        let pdas = Pdas::new(&ixs, &defs());
+        let generated = generate_client_accounts_struct(
+            "Open",
+            &ixs[0].accounts,
+            &pdas.derived_accounts(&ixs[0]),
+            None,
+        )
+        .to_string();
+        for name in ["pool", "vault", "log", "late"] {
+            let ident = format_ident!("{}", name);
+            let field = quote! { pub #ident: Option<Pubkey> };
+            assert!(
+                generated.contains(&field.to_string()),
+                "{} is not optional",
+                name
+            );
+        }
+        for name in ["early", "authority", "position"] {
+            let ident = format_ident!("{}", name);
+            let field = quote! { pub #ident: Pubkey };
+            assert!(
+                generated.contains(&field.to_string()),
+                "{} is optional",
+                name
+            );
+        }
+    }
+}
@@ -1,60 +1,107 @@
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     env, fs,
     path::PathBuf,
 };
 // use std::collections::HashMap;
 // use anchor_lang::solana_program::hash::hash;
 
-use darling::{util::PathList, FromMeta};
-use heck::ToPascalCase;
+use darling::{
+    util::{PathList, SpannedValue},
+    FromMeta,
+};
 use proc_macro2::{Ident, TokenStream};
-use quote::{format_ident, quote};
+use quote::quote;
 
-use crate::{generate_accounts, generate_ix_handlers, generate_ix_structs, generate_typedefs, GEN_VERSION};
+use crate::{generate_accounts, generate_account_filter, generate_client, generate_constants, generate_cpi_helpers, generate_decoded_instruction, generate_errors, generate_events, generate_ix_args_structs, generate_ix_discriminators, generate_ix_handlers, generate_ix_structs, generate_serde_derives, generate_typedefs, ix_struct_ident, IdlTypeDefinition, SerdeOpts, Pdas, type_ident, EnumFields, IdlField, IdlType, IdlTypeDefinitionTy, GEN_VERSION};
 
 #[derive(Default, FromMeta)]
 pub struct GeneratorOptions {
     /// Path to the IDL.
-    pub idl_path: String,
+    pub idl_path: SpannedValue<String>,
     /// List of zero copy structs.
     pub zero_copy: Option<PathList>,
     /// List of `repr(packed)` structs.
     pub packed: Option<PathList>,
+    /// Typedefs to replace with existing types, as `Name = "path::to::Type"`.
+    pub type_map: Option<HashMap<String, String>>,
+    /// Chooses zero copy and `repr(packed)` structs from the IDL, in addition to the listed ones.
+    /// Accounts made zero copy are left out of `AccountType`.
+    #[darling(default)]
+    pub auto_layout: bool,
+    /// Derives serde's `Serialize` and `Deserialize`, as `serde` or `serde(ints_as_strings)`.
+    /// Defaults to on when the `serde` feature is enabled.
+    pub serde: Option<SerdeOpts>,
 }
 
-fn path_list_to_string(list: Option<&PathList>) -> HashSet<String> {
+fn path_list_to_string(list: Option<&PathList>) -> syn::Result<HashSet<String>> {
     list.map(|el| {
         el.iter()
-            .map(|el| el.get_ident().unwrap().to_string())
+            .map(|el| {
+                el.get_ident()
+                    .map(|ident| ident.to_string())
+                    .ok_or_else(|| syn::Error::new_spanned(el, "expected the name of a struct"))
+            })
             .collect()
     })
-    .unwrap_or_default()
+    .unwrap_or_else(|| Ok(HashSet::new()))
 }
 
 impl GeneratorOptions {
-    pub fn to_generator(&self) -> Generator {
-        let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-        let path = PathBuf::from(cargo_manifest_dir).join(&self.idl_path);
-        let idl_contents = fs::read_to_string(path).unwrap();
-        let idl: anchor_syn::idl::Idl = serde_json::from_str(&idl_contents).unwrap();
+    /// Reads, parses and validates the IDL. Errors point at the IDL path in the macro invocation.
+    pub fn to_generator(&self) -> syn::Result<Generator> {
+        let span = self.idl_path.span();
+        let cargo_manifest_dir = env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| syn::Error::new(span, "CARGO_MANIFEST_DIR is not set"))?;
+        let path = PathBuf::from(cargo_manifest_dir).join(self.idl_path.as_str());
+        let idl_contents = fs::read_to_string(&path).map_err(|e| {
+            syn::Error::new(span, format!("failed to read IDL {}: {}", path.display(), e))
+        })?;
+        let idl = crate::parse_idl(&idl_contents).map_err(|e| {
+            syn::Error::new(span, format!("failed to parse IDL {}: {}", path.display(), e))
+        })?;
+        crate::validate_idl(&idl).map_err(|e| {
+            syn::Error::new(span, format!("invalid IDL {}: {}", path.display(), e))
+        })?;
 
-        let zero_copy = path_list_to_string(self.zero_copy.as_ref());
-        let packed = path_list_to_string(self.packed.as_ref());
+        let type_map: BTreeMap<String, String> =
+            self.type_map.clone().unwrap_or_default().into_iter().collect();
+        crate::validate_type_map(&idl, &type_map).map_err(|e| {
+            syn::Error::new(span, format!("invalid type_map for {}: {}", path.display(), e))
+        })?;
 
-        let mut struct_opts: BTreeMap<String, StructOpts> = BTreeMap::new();
-        let all_structs: HashSet<&String> = zero_copy.union(&packed).collect::<HashSet<_>>();
-        all_structs.into_iter().for_each(|name| {
-            struct_opts.insert(
-                name.to_string(),
-                StructOpts {
-                    zero_copy: zero_copy.contains(name),
-                    packed: packed.contains(name),
-                },
-            );
-        });
+        let zero_copy = path_list_to_string(self.zero_copy.as_ref())?;
+        let packed = path_list_to_string(self.packed.as_ref())?;
 
-        Generator { idl, struct_opts }
+        let mut gen = Generator::new(idl, &zero_copy, &packed).with_type_map(&type_map);
+        if self.auto_layout {
+            gen = gen.with_auto_layout();
+        }
+        let serde = self
+            .serde
+            .or_else(|| cfg!(feature = "serde").then(SerdeOpts::default));
+        if let Some(serde) = serde {
+            gen = gen.with_serde(serde);
+        }
+        Ok(gen)
+    }
+}
+
+fn map_field_types(fields: &mut [IdlField], type_map: &BTreeMap<String, String>) {
+    fields.iter_mut().for_each(|field| map_type(&mut field.ty, type_map));
+}
+
+fn map_type(ty: &mut IdlType, type_map: &BTreeMap<String, String>) {
+    match ty {
+        IdlType::Defined(name) => {
+            if let Some(path) = type_map.get(name) {
+                *name = path.clone();
+            }
+        }
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            map_type(inner, type_map)
+        }
+        _ => {}
     }
 }
 
@@ -65,19 +112,156 @@ pub struct StructOpts {
 }
 
 pub struct Generator {
-    pub idl: anchor_syn::idl::Idl,
+    pub idl: crate::Idl,
     pub struct_opts: BTreeMap<String, StructOpts>,
+    pub serde: Option<SerdeOpts>,
 }
 
 impl Generator {
+    /// Creates a generator for an IDL, with the names of the structs to make zero copy or `repr(packed)`.
+    pub fn new(idl: crate::Idl, zero_copy: &HashSet<String>, packed: &HashSet<String>) -> Generator {
+        let mut struct_opts: BTreeMap<String, StructOpts> = BTreeMap::new();
+        let all_structs: HashSet<&String> = zero_copy.union(packed).collect::<HashSet<_>>();
+        all_structs.into_iter().for_each(|name| {
+            struct_opts.insert(
+                name.to_string(),
+                StructOpts {
+                    zero_copy: zero_copy.contains(name),
+                    packed: packed.contains(name),
+                },
+            );
+        });
+
+        Generator {
+            idl,
+            struct_opts,
+            serde: None,
+        }
+    }
+
+    /// Replaces the typedefs in `type_map` with the paths of existing types, so that they are
+    /// not generated and every field of that type uses the existing type instead.
+    pub fn with_type_map(mut self, type_map: &BTreeMap<String, String>) -> Generator {
+        if type_map.is_empty() {
+            return self;
+        }
+        let idl = &mut self.idl;
+        idl.types.retain(|def| !type_map.contains_key(&def.name));
+        for def in idl.types.iter_mut().chain(idl.accounts.iter_mut()) {
+            match &mut def.ty {
+                IdlTypeDefinitionTy::Struct { fields } => map_field_types(fields, type_map),
+                IdlTypeDefinitionTy::Enum { variants } => {
+                    for variant in variants {
+                        match &mut variant.fields {
+                            Some(EnumFields::Named(fields)) => map_field_types(fields, type_map),
+                            Some(EnumFields::Tuple(types)) => {
+                                types.iter_mut().for_each(|ty| map_type(ty, type_map))
+                            }
+                            None => {}
+                        }
+                    }
+                }
+            }
+        }
+        for ix in &mut idl.instructions {
+            map_field_types(&mut ix.args, type_map);
+        }
+        for event in idl.events.iter_mut().flatten() {
+            event.fields.iter_mut().for_each(|field| map_type(&mut field.ty, type_map));
+        }
+        self
+    }
+
+    /// Makes structs zero copy or `repr(packed)` as chosen by [crate::auto_struct_opts]. Structs
+    /// which were listed explicitly keep their options.
+    ///
+    /// Zero copy accounts are not Borsh types, so the accounts this makes zero copy are left out of
+    /// `AccountType` and have to be read with `AccountLoader` or `bytemuck` instead.
+    ///
+    /// This looks at the typedefs of the IDL, so it should come after [Generator::with_type_map].
+    pub fn with_auto_layout(mut self) -> Generator {
+        for (name, opts) in crate::auto_struct_opts(&self.idl) {
+            self.struct_opts.entry(name).or_insert(opts);
+        }
+        self
+    }
+
+    /// Derives serde's `Serialize` and `Deserialize` on the typedefs, accounts, events and
+    /// instruction args, and on the `AccountType`, `InstructionType` and `EventType` enums. See [crate::SerdeOpts] for the encodings.
+    pub fn with_serde(mut self, opts: SerdeOpts) -> Generator {
+        self.serde = Some(opts);
+        self
+    }
+
     pub fn generate_cpi_interface(&self) -> TokenStream {
         let idl = &self.idl;
-        let program_name: Ident = format_ident!("{}", idl.name);
+        let program_name: Ident = type_ident(&idl.name).ident;
         
-        let accounts = generate_accounts(&idl.types, &idl.accounts, &self.struct_opts);
-        let typedefs = generate_typedefs(&idl.types, &self.struct_opts);
+        let accounts = generate_accounts(&idl.types, &idl.accounts, &self.struct_opts, self.serde);
+        let typedefs = generate_typedefs(&idl.types, &self.struct_opts, self.serde);
         let ix_handlers = generate_ix_handlers(&idl.instructions);
         let ix_structs = generate_ix_structs(&idl.instructions);
+        let ix_args = generate_ix_args_structs(&idl.instructions, self.serde);
+        let ix_discriminators = generate_ix_discriminators(&idl.instructions);
+        let account_filter = generate_account_filter();
+        let defs: Vec<IdlTypeDefinition> =
+            idl.types.iter().chain(&idl.accounts).cloned().collect();
+        let pdas = Pdas::new(&idl.instructions, &defs);
+        let client = generate_client(&idl.instructions, &pdas, self.serde);
+        let pda = if pdas.is_empty() {
+            quote! {}
+        } else {
+            let helpers = pdas.generate_helpers();
+            quote! {
+                pub mod pda {
+                    //! Functions deriving the addresses of the program's PDAs.
+                    use super::*;
+                    #helpers
+                }
+            }
+        };
+        let cpi_helpers = generate_cpi_helpers(&idl.instructions);
+        let events = match &idl.events {
+            Some(events) if !events.is_empty() => {
+                let events = generate_events(events, self.serde);
+                quote! {
+                    pub mod events {
+                        //! Events emitted by the program.
+                        use super::*;
+                        #events
+                    }
+
+                    pub use events::*;
+                }
+            }
+            _ => quote! {},
+        };
+        let constants = if idl.constants.is_empty() {
+            quote! {}
+        } else {
+            let constants = generate_constants(&idl.constants);
+            quote! {
+                pub mod constants {
+                    //! Constants declared by the program. Constants whose values refer to
+                    //! items outside of the IDL are left out.
+                    use super::*;
+                    #constants
+                }
+            }
+        };
+        let errors = match &idl.errors {
+            Some(errors) if !errors.is_empty() => {
+                let errors = generate_errors(errors);
+                quote! {
+                    pub mod errors {
+                        //! Errors returned by the program.
+                        use super::*;
+                        #errors
+                    }
+                }
+            }
+            _ => quote! {},
+        };
 
         let docs = format!(
             " Anchor CPI crate generated from {} v{} using [anchor-gen](https://crates.io/crates/anchor-gen) v{}.",
@@ -107,6 +291,34 @@ impl Generator {
                 #ix_structs
             }
 
+            pub mod args {
+                //! Args of the program's instructions.
+                use super::*;
+                #ix_args
+            }
+
+            pub mod client {
+                //! Off-chain builders for the program's instructions.
+                use super::*;
+                #client
+            }
+
+            pub mod invoke {
+                //! Helpers for invoking the program's instructions via CPI.
+                use super::*;
+                #cpi_helpers
+            }
+
+            #events
+
+            #errors
+
+            #constants
+
+            #pda
+
+            #account_filter
+
             use ix_accounts::*;
             pub use state::*;
             pub use typedefs::*;
@@ -118,16 +330,120 @@ impl Generator {
                 use super::*;
                 #ix_handlers
             }
+
+            #ix_discriminators
         }
     }
     
+    /// Generates the `AccountType`, `InstructionType` and `EventType` enums, which decode
+    /// any account, instruction or event of the program, and the `DecodedInstruction` enum
+    /// returned by `InstructionType::decode_with_accounts`.
+    pub fn generate_type_enums(&self) -> TokenStream {
+        let acct_variants = self.account_types().into_iter().zip(self.account_discriminators()).map(|(ident, discrim)| {
+            let variant_name = ident.clone();
+            quote! { #variant_name(#ident) = [#(#discrim),*] }
+        });
+        let ix_variants = self.instruction_types().into_iter().zip(self.instruction_discriminators()).map(|(ident, discrim)| {
+            let variant_name = ident.clone();
+            quote! { #variant_name(args::#ident) = [#(#discrim),*] }
+        });
+        let derive_serde = generate_serde_derives(self.serde);
+        let decoded_instruction = generate_decoded_instruction(&self.idl.instructions, self.serde);
+        let mut ts = quote! {
+            anchor_gen::derive_account_type!(
+                #derive_serde
+                pub enum AccountType {
+                    #(#acct_variants,)*
+                }
+            );
+
+            anchor_gen::derive_instruction_type!(
+                #derive_serde
+                pub enum InstructionType {
+                    #(#ix_variants,)*
+                }
+            );
+
+            #decoded_instruction
+        };
+
+        let event_types = self.event_types();
+        if !event_types.is_empty() {
+            let event_variants = event_types.into_iter().zip(self.event_discriminators()).map(|(ident, discrim)| {
+                let variant_name = ident.clone();
+                quote! { #variant_name(events::#ident) = [#(#discrim),*] }
+            });
+            ts.extend(quote! {
+                anchor_gen::derive_event_type!(
+                    #derive_serde
+                    pub enum EventType {
+                        #(#event_variants,)*
+                    }
+                );
+            });
+        }
+        ts
+    }
+
+    /// Accounts which are decoded with Borsh, which excludes the zero copy ones.
+    fn borsh_accounts(&self) -> impl Iterator<Item = &crate::IdlTypeDefinition> {
+        self.idl.accounts.iter().filter(|d| {
+            !self.struct_opts.get(&d.name).map(|opts| opts.zero_copy).unwrap_or(false)
+        })
+    }
+
+    /// Names of the accounts in `AccountType`. Zero copy accounts are not Borsh types, so they are left out.
     pub fn account_types(&self) -> Vec<Ident> {
-        let acct_idents: Vec<Ident> = self.idl.accounts.iter().map(|d| format_ident!("{}", d.name)).collect();
+        let acct_idents: Vec<Ident> = self.borsh_accounts().map(|d| type_ident(&d.name).ident).collect();
         acct_idents
     }
 
+    pub fn event_types(&self) -> Vec<Ident> {
+        let event_idents: Vec<Ident> = self.idl.events.iter().flatten().map(|d| type_ident(&d.name).ident).collect();
+        event_idents
+    }
+
     pub fn instruction_types(&self) -> Vec<Ident> {
-        let ix_idents: Vec<Ident> = self.idl.instructions.iter().map(|d| format_ident!("{}", d.name.to_pascal_case())).collect();
+        let ix_idents: Vec<Ident> = self.idl.instructions.iter().map(|d| ix_struct_ident(&d.name)).collect();
         ix_idents
     }
+
+    /// Discriminators of the accounts, in the same order as [Self::account_types].
+    pub fn account_discriminators(&self) -> Vec<Vec<u8>> {
+        self.borsh_accounts().map(crate::idl_account_discriminator).collect()
+    }
+
+    /// Discriminators of the events, in the same order as [Self::event_types].
+    pub fn event_discriminators(&self) -> Vec<Vec<u8>> {
+        self.idl.events.iter().flatten().map(crate::idl_event_discriminator).collect()
+    }
+
+    /// Discriminators of the instructions, in the same order as [Self::instruction_types].
+    pub fn instruction_discriminators(&self) -> Vec<Vec<u8>> {
+        self.idl.instructions.iter().map(crate::idl_instruction_discriminator).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_layout_leaves_zero_copy_accounts_out_of_account_type() {
+        let idl: crate::Idl = serde_json::from_str(
+            r#"{"version": "0.1.0", "name": "ticks", "instructions": [], "accounts": [
+                {"name": "TickArray", "type": {"kind": "struct", "fields": [
+                    {"name": "ticks", "type": {"array": ["u64", 128]}}]}},
+                {"name": "Position", "type": {"kind": "struct", "fields": [
+                    {"name": "liquidity", "type": "u64"}]}}]}"#,
+        )
+        .unwrap();
+        let gen = Generator::new(idl, &HashSet::new(), &HashSet::new());
+        assert_eq!(gen.account_types(), ["TickArray", "Position"]);
+
+        let gen = gen.with_auto_layout();
+        assert!(gen.struct_opts["TickArray"].zero_copy);
+        assert_eq!(gen.account_types(), ["Position"]);
+        assert_eq!(gen.account_discriminators().len(), 1);
+    }
 }
@@ -0,0 +1,91 @@
+//! Serde derives for the generated types, added by [Generator::with_serde](crate::Generator::with_serde).
+//!
+//! The derives refer to serde and serde_with through `anchor_gen::__serde`, so the generated
+//! crate needs the `serde` feature of `anchor-gen`.
+
+use darling::FromMeta;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::IdlType;
+
+const SERDE_WITH: &str = "anchor_gen::__serde::serde_with";
+
+/// Encodings of the serde derives.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerdeOpts {
+    /// Serializes 64 and 128-bit integers as strings, since JSON parsers such as JavaScript's
+    /// lose precision on numbers above 2^53.
+    pub ints_as_strings: bool,
+}
+
+/// Parses `serde` alone, or with options such as `serde(ints_as_strings)`.
+impl FromMeta for SerdeOpts {
+    fn from_word() -> darling::Result<Self> {
+        Ok(SerdeOpts::default())
+    }
+
+    fn from_list(items: &[syn::NestedMeta]) -> darling::Result<Self> {
+        #[derive(FromMeta)]
+        struct Options {
+            #[darling(default)]
+            ints_as_strings: bool,
+        }
+        let options = Options::from_list(items)?;
+        Ok(SerdeOpts {
+            ints_as_strings: options.ints_as_strings,
+        })
+    }
+}
+
+/// Generates the attributes deriving `Serialize` and `Deserialize` on a struct or enum, if serde
+/// is enabled. They go after the Anchor attributes, so that serde sees a `repr(packed)`.
+pub fn generate_serde_derives(serde: Option<SerdeOpts>) -> TokenStream {
+    if serde.is_none() {
+        return quote! {};
+    }
+    quote! {
+        #[anchor_gen::__serde::serde_with::serde_as(crate = "anchor_gen::__serde::serde_with")]
+        #[derive(anchor_gen::__serde::serde::Serialize, anchor_gen::__serde::serde::Deserialize)]
+        #[serde(crate = "anchor_gen::__serde::serde")]
+    }
+}
+
+/// Generates the `serde_as` attribute of a field whose type isn't serialized as is.
+pub fn generate_serde_field_attr(ty: &IdlType, serde: Option<SerdeOpts>) -> TokenStream {
+    match serde.and_then(|opts| serde_as_type(ty, opts)) {
+        Some(as_type) => quote! {
+            #[serde_as(as = #as_type)]
+        },
+        None => quote! {},
+    }
+}
+
+/// Returns the `serde_as` type of a field, or `None` if the type's own serde impls are used:
+///
+/// - public keys are base58 strings;
+/// - byte vectors and arrays are base64 strings;
+/// - 64 and 128-bit integers are strings if [SerdeOpts::ints_as_strings] is set;
+/// - arrays of more than 32 items go through serde_with, since serde stops at 32.
+fn serde_as_type(ty: &IdlType, opts: SerdeOpts) -> Option<String> {
+    match ty {
+        IdlType::PublicKey => Some(format!("{}::DisplayFromStr", SERDE_WITH)),
+        IdlType::U64 | IdlType::I64 | IdlType::U128 | IdlType::I128 if opts.ints_as_strings => {
+            Some(format!("{}::DisplayFromStr", SERDE_WITH))
+        }
+        IdlType::Bytes => Some(format!("{}::base64::Base64", SERDE_WITH)),
+        IdlType::Array(inner, _) if **inner == IdlType::U8 => {
+            Some(format!("{}::base64::Base64", SERDE_WITH))
+        }
+        IdlType::Array(inner, len) => match serde_as_type(inner, opts) {
+            Some(inner) => Some(format!("[{}; {}]", inner, len)),
+            None if *len > 32 => Some(format!("[_; {}]", len)),
+            None => None,
+        },
+        IdlType::Option(inner) => {
+            serde_as_type(inner, opts).map(|inner| format!("Option<{}>", inner))
+        }
+        IdlType::Vec(inner) => serde_as_type(inner, opts).map(|inner| format!("Vec<{}>", inner)),
+        _ => None,
+    }
+}
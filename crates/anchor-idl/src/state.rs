@@ -1,19 +1,28 @@
 use std::collections::BTreeMap;
 
-use anchor_syn::idl::{IdlField, IdlTypeDefinition};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use crate::{generate_fields, get_field_list_properties, StructOpts};
+use crate::{
+    c_field_offsets, c_struct_size, generate_default_impl, generate_discriminator_const,
+    generate_fields, generate_leading_docs, generate_serde_derives, generate_struct_size,
+    get_field_list_properties, get_field_list_size, get_type_size, idl_account_discriminator,
+    type_ident, unique_field_idents, IdlField, IdlType, IdlTypeDefinition, SerdeOpts, StructOpts,
+};
 
 /// Generates an account state struct.
 pub fn generate_account(
     defs: &[IdlTypeDefinition],
     account_name: &str,
+    docs: Option<&[String]>,
     fields: &[IdlField],
-    opts: StructOpts,
+    discriminator: &[u8],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    serde: Option<SerdeOpts>,
 ) -> TokenStream {
+    let opts = struct_opts.get(account_name).copied().unwrap_or_default();
     let props = get_field_list_properties(defs, fields);
+    let struct_name = type_ident(account_name);
 
     let derive_copy = if props.can_copy && !opts.zero_copy {
         quote! {
@@ -29,6 +38,11 @@ pub fn generate_account(
     } else {
         quote! {}
     };
+    let impl_default = if !props.can_derive_default && props.can_impl_default {
+        generate_default_impl(defs, &struct_name.ident, fields)
+    } else {
+        quote! {}
+    };
     let derive_account = if opts.zero_copy {
         let repr = if opts.packed {
             quote! {
@@ -49,17 +63,148 @@ pub fn generate_account(
         }
     };
 
+    let idl_docs = generate_leading_docs(docs);
     let doc = format!(" Account: {}", account_name);
-    let struct_name = format_ident!("{}", account_name);
-    let fields_rendered = generate_fields(fields);
+    let alias = struct_name.doc_alias();
+    let fields_rendered = generate_fields(fields, serde);
+    let derive_serde = generate_serde_derives(serde);
+    let filters = generate_account_filters(defs, fields, discriminator.len(), struct_opts, opts);
+    // Zero copy accounts aren't Borsh-serializable, so only their fixed size is known.
+    let size = if opts.zero_copy && get_field_list_size(defs, fields).fixed().is_none() {
+        quote! {}
+    } else {
+        generate_struct_size(defs, struct_opts, fields, discriminator.len(), opts)
+    };
+    let discriminator = generate_discriminator_const(discriminator);
     quote! {
         #derive_account
+        #idl_docs
         #[doc = #doc]
+        #alias
         #derive_copy
         #derive_default
+        #derive_serde
         pub struct #struct_name {
             #fields_rendered
         }
+
+        impl #struct_name {
+            #discriminator
+            #size
+            #filters
+        }
+
+        #impl_default
+    }
+}
+
+/// Generates the `getProgramAccounts` filters of an account: one matching its discriminator and,
+/// if every field has a fixed size, one matching its size, a table of the field offsets and a
+/// filter for each field.
+///
+/// The fields of `repr(C)` zero copy accounts are at their offsets in memory, which are only
+/// generated if they are known from the IDL, as is the size filter. Fields of zero copy accounts are only filtered on
+/// if they don't contain a defined type, since those aren't Borsh-serializable.
+pub fn generate_account_filters(
+    defs: &[IdlTypeDefinition],
+    fields: &[IdlField],
+    discriminator_len: usize,
+    struct_opts: &BTreeMap<String, StructOpts>,
+    opts: StructOpts,
+) -> TokenStream {
+    let discriminator_filter = quote! {
+        /// Matches accounts of this type by their discriminator.
+        pub fn discriminator_filter() -> AccountFilter {
+            AccountFilter::Memcmp {
+                offset: 0,
+                bytes: Self::DISCRIMINATOR.to_vec(),
+            }
+        }
+    };
+    if get_field_list_size(defs, fields).fixed().is_none() {
+        return discriminator_filter;
+    }
+    // Like `LEN`, which it matches, the size of `repr(C)` accounts is left out when it depends
+    // on the target.
+    let repr_c = opts.zero_copy && !opts.packed;
+    let data_size_filter = if repr_c && c_struct_size(defs, struct_opts, fields).is_none() {
+        quote! {}
+    } else {
+        quote! {
+            /// Matches accounts of this type by the size of their data.
+            pub fn data_size_filter() -> AccountFilter {
+                AccountFilter::DataSize(Self::LEN as u64)
+            }
+        }
+    };
+
+    let offsets = if repr_c {
+        c_field_offsets(defs, struct_opts, fields)
+    } else {
+        let sizes = fields
+            .iter()
+            .map(|field| get_type_size(defs, &field.ty).fixed().unwrap());
+        Some(
+            sizes
+                .scan(0, |offset, size| {
+                    let field_offset = *offset;
+                    *offset += size;
+                    Some(field_offset)
+                })
+                .collect(),
+        )
+    };
+    let offsets = match offsets {
+        Some(offsets) => offsets,
+        None => {
+            return quote! {
+                #discriminator_filter
+                #data_size_filter
+            }
+        }
+    };
+
+    let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+    let mut field_offsets = vec![];
+    let mut field_filters = vec![];
+    for ((field, name), offset) in fields.iter().zip(names).zip(offsets) {
+        let field_offset = discriminator_len + offset;
+        let field_name = name.unraw();
+        field_offsets.push(quote! { (#field_name, #field_offset) });
+        if opts.zero_copy && contains_defined(&field.ty) {
+            continue;
+        }
+        let filter_name = format_ident!("filter_{}", field_name);
+        let ty: TokenStream = crate::ty_to_rust_type(&field.ty).parse().unwrap();
+        let doc = format!(" Matches accounts whose `{}` is `value`.", field_name);
+        field_filters.push(quote! {
+            #[doc = #doc]
+            pub fn #filter_name(value: &#ty) -> AccountFilter {
+                AccountFilter::Memcmp {
+                    offset: #field_offset,
+                    bytes: anchor_lang::AnchorSerialize::try_to_vec(value).unwrap(),
+                }
+            }
+        });
+    }
+    let len = field_offsets.len();
+    quote! {
+        /// Names and offsets of the fields in the account data, which starts with the discriminator.
+        pub const FIELD_OFFSETS: [(&'static str, usize); #len] = [#(#field_offsets),*];
+
+        #discriminator_filter
+        #data_size_filter
+        #(#field_filters)*
+    }
+}
+
+fn contains_defined(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Defined(_) => true,
+        IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+            contains_defined(inner)
+        }
+        _ => false,
     }
 }
 
@@ -68,17 +213,64 @@ pub fn generate_accounts(
     typedefs: &[IdlTypeDefinition],
     account_defs: &[IdlTypeDefinition],
     struct_opts: &BTreeMap<String, StructOpts>,
+    serde: Option<SerdeOpts>,
 ) -> TokenStream {
     let defined = account_defs.iter().map(|def| match &def.ty {
-        anchor_syn::idl::IdlTypeDefinitionTy::Struct { fields } => {
-            let opts = struct_opts.get(&def.name).copied().unwrap_or_default();
-            generate_account(typedefs, &def.name, fields, opts)
+        crate::IdlTypeDefinitionTy::Struct { fields } => {
+            let discriminator = idl_account_discriminator(def);
+            generate_account(
+                typedefs,
+                &def.name,
+                def.docs.as_deref(),
+                fields,
+                &discriminator,
+                struct_opts,
+                serde,
+            )
         }
-        anchor_syn::idl::IdlTypeDefinitionTy::Enum { .. } => {
-            panic!("unexpected enum account");
+        // `validate_idl` rejects enum accounts before anything is generated.
+        crate::IdlTypeDefinitionTy::Enum { .. } => {
+            unreachable!("account `{}` is an enum", def.name)
         }
     });
     quote! {
         #(#defined)*
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(fields: &str) -> String {
+        let fields: Vec<IdlField> = serde_json::from_str(fields).unwrap();
+        let opts = StructOpts {
+            zero_copy: true,
+            packed: false,
+        };
+        generate_account_filters(&[], &fields, 8, &BTreeMap::new(), opts).to_string()
+    }
+
+    #[test]
+    fn repr_c_filters_depending_on_u128_alignment_are_left_out() {
+        // The offsets are the same on every target, but the size is 8 + 32 bytes where `u128` is
+        // aligned to 16 bytes and 8 + 24 on BPF.
+        let generated = filters(r#"[{"name": "a", "type": "u128"}, {"name": "b", "type": "u64"}]"#);
+        assert!(generated.contains("FIELD_OFFSETS"));
+        assert!(generated.contains("filter_b"));
+        assert!(!generated.contains("data_size_filter"));
+
+        // Neither are the offsets.
+        let generated = filters(r#"[{"name": "a", "type": "u64"}, {"name": "b", "type": "u128"}]"#);
+        assert!(generated.contains("discriminator_filter"));
+        assert!(!generated.contains("FIELD_OFFSETS"));
+        assert!(!generated.contains("data_size_filter"));
+
+        let generated = filters(
+            r#"[{"name": "a", "type": "u8"}, {"name": "b", "type": "u64"},
+                {"name": "c", "type": "publicKey"}]"#,
+        );
+        assert!(generated.contains("data_size_filter"));
+        assert!(generated.contains(r#"("c" , 24usize)"#));
+    }
+}
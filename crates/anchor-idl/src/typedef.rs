@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
 
-use anchor_syn::idl::{EnumFields, IdlEnumVariant, IdlField, IdlType, IdlTypeDefinition};
-use heck::ToSnakeCase;
+use crate::{
+    c_struct_size, generate_docs, generate_serde_derives, generate_serde_field_attr, type_ident,
+    unique_field_idents, EnumFields, IdlEnumVariant, IdlField, IdlType, IdlTypeDefinition,
+    SerdeOpts,
+};
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 
@@ -11,6 +14,9 @@ use crate::StructOpts;
 pub struct FieldListProperties {
     pub can_copy: bool,
     pub can_derive_default: bool,
+    /// Whether a default value can be written out when `Default` can't be derived, such as
+    /// `[Default::default(); 88]` for arrays of more than 32 items.
+    pub can_impl_default: bool,
 }
 
 pub fn get_field_list_properties(
@@ -31,14 +37,17 @@ pub fn get_type_list_properties(
         FieldListProperties {
             can_copy: true,
             can_derive_default: true,
+            can_impl_default: true,
         },
         |acc, el| {
             let inner_props = get_type_properties(defs, el);
             let can_copy = acc.can_copy && inner_props.can_copy;
             let can_derive_default = acc.can_derive_default && inner_props.can_derive_default;
+            let can_impl_default = acc.can_impl_default && inner_props.can_impl_default;
             FieldListProperties {
                 can_copy,
                 can_derive_default,
+                can_impl_default,
             }
         },
     )
@@ -52,6 +61,7 @@ pub fn get_variant_list_properties(
         FieldListProperties {
             can_copy: true,
             can_derive_default: true,
+            can_impl_default: true,
         },
         |acc, el| {
             let props = match &el.fields {
@@ -62,6 +72,7 @@ pub fn get_variant_list_properties(
             FieldListProperties {
                 can_copy: acc.can_copy && props.can_copy,
                 can_derive_default: acc.can_derive_default && props.can_derive_default,
+                can_impl_default: acc.can_impl_default && props.can_impl_default,
             }
         },
     )
@@ -85,45 +96,179 @@ pub fn get_type_properties(defs: &[IdlTypeDefinition], ty: &IdlType) -> FieldLis
         | IdlType::PublicKey => FieldListProperties {
             can_copy: true,
             can_derive_default: true,
+            can_impl_default: true,
         },
-        IdlType::Bytes => FieldListProperties {
-            can_copy: false,
-            can_derive_default: false,
-        },
-        IdlType::String | IdlType::Vec(_) => FieldListProperties {
+        IdlType::Bytes | IdlType::String | IdlType::Vec(_) => FieldListProperties {
             can_copy: false,
             can_derive_default: true,
+            can_impl_default: true,
         },
-        IdlType::Defined(inner) => {
-            let def = defs.iter().find(|def| def.name == *inner).unwrap();
-            match &def.ty {
-                anchor_syn::idl::IdlTypeDefinitionTy::Struct { fields } => {
-                    get_field_list_properties(defs, fields)
+        IdlType::Defined(inner) => match defs.iter().find(|def| def.name == *inner) {
+            Some(def) => match &def.ty {
+                // Structs which can't derive `Default` get an impl instead, if possible.
+                crate::IdlTypeDefinitionTy::Struct { fields } => {
+                    let props = get_field_list_properties(defs, fields);
+                    FieldListProperties {
+                        can_derive_default: props.can_impl_default,
+                        ..props
+                    }
                 }
-                anchor_syn::idl::IdlTypeDefinitionTy::Enum { variants } => {
-                    get_variant_list_properties(defs, variants)
+                // Enums default to their first variant, if its fields can be defaulted.
+                crate::IdlTypeDefinitionTy::Enum { variants } => {
+                    let default_props =
+                        get_variant_list_properties(defs, &variants[..variants.len().min(1)]);
+                    let has_default = !variants.is_empty() && default_props.can_impl_default;
+                    FieldListProperties {
+                        can_copy: get_variant_list_properties(defs, variants).can_copy,
+                        can_derive_default: has_default,
+                        can_impl_default: has_default,
+                    }
                 }
-            }
-        }
-        IdlType::Option(inner) => get_type_properties(defs, inner),
+            },
+            // Types outside of `defs`, such as accounts, are assumed to be neither Copy nor Default.
+            None => FieldListProperties::default(),
+        },
+        // `Option` defaults to `None`, whatever its inner type.
+        IdlType::Option(inner) => FieldListProperties {
+            can_copy: get_type_properties(defs, inner).can_copy,
+            can_derive_default: true,
+            can_impl_default: true,
+        },
         IdlType::Array(inner, len) => {
             let inner = get_type_properties(defs, inner);
             let can_derive_array_len = *len <= 32;
+            let can_derive_default = can_derive_array_len && inner.can_derive_default;
             FieldListProperties {
                 can_copy: inner.can_copy,
-                can_derive_default: can_derive_array_len && inner.can_derive_default,
+                can_derive_default,
+                can_impl_default: can_derive_default || inner.can_impl_default,
             }
         }
     }
 }
 
+/// Size of the Borsh serialization of a type.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TypeSize {
+    /// Every value has the same size.
+    Fixed(usize),
+    /// Values have different sizes up to a maximum, such as options.
+    Bounded(usize),
+    /// Values can be of any size, such as strings and vectors.
+    Unbounded,
+}
+
+impl TypeSize {
+    /// Returns the size of every value, if it's the same.
+    pub fn fixed(self) -> Option<usize> {
+        match self {
+            TypeSize::Fixed(size) => Some(size),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the largest value, if there is one.
+    pub fn max(self) -> Option<usize> {
+        match self {
+            TypeSize::Fixed(size) | TypeSize::Bounded(size) => Some(size),
+            TypeSize::Unbounded => None,
+        }
+    }
+
+    /// Size of a value of this type followed by one of `other`.
+    fn then(self, other: TypeSize) -> TypeSize {
+        match (self, other) {
+            (TypeSize::Fixed(a), TypeSize::Fixed(b)) => TypeSize::Fixed(a + b),
+            _ => match self.max().zip(other.max()) {
+                Some((a, b)) => TypeSize::Bounded(a + b),
+                None => TypeSize::Unbounded,
+            },
+        }
+    }
+
+    /// Size of a value which is either of this type or of `other`.
+    fn either(self, other: TypeSize) -> TypeSize {
+        match (self, other) {
+            (TypeSize::Fixed(a), TypeSize::Fixed(b)) if a == b => TypeSize::Fixed(a),
+            _ => match self.max().zip(other.max()) {
+                Some((a, b)) => TypeSize::Bounded(a.max(b)),
+                None => TypeSize::Unbounded,
+            },
+        }
+    }
+}
+
+pub fn get_field_list_size(defs: &[IdlTypeDefinition], fields: &[IdlField]) -> TypeSize {
+    fields.iter().fold(TypeSize::Fixed(0), |acc, field| {
+        acc.then(get_type_size(defs, &field.ty))
+    })
+}
+
+pub fn get_type_list_size(defs: &[IdlTypeDefinition], types: &[IdlType]) -> TypeSize {
+    types.iter().fold(TypeSize::Fixed(0), |acc, ty| {
+        acc.then(get_type_size(defs, ty))
+    })
+}
+
+pub fn get_variant_list_size(defs: &[IdlTypeDefinition], variants: &[IdlEnumVariant]) -> TypeSize {
+    let fields_size = variants
+        .iter()
+        .map(|variant| match &variant.fields {
+            Some(EnumFields::Named(fields)) => get_field_list_size(defs, fields),
+            Some(EnumFields::Tuple(types)) => get_type_list_size(defs, types),
+            None => TypeSize::Fixed(0),
+        })
+        .reduce(TypeSize::either)
+        .unwrap_or(TypeSize::Fixed(0));
+    // Borsh writes the variant index as a byte, followed by the fields of the variant.
+    TypeSize::Fixed(1).then(fields_size)
+}
+
+pub fn get_type_size(defs: &[IdlTypeDefinition], ty: &IdlType) -> TypeSize {
+    match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 => TypeSize::Fixed(1),
+        IdlType::U16 | IdlType::I16 => TypeSize::Fixed(2),
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => TypeSize::Fixed(4),
+        IdlType::U64 | IdlType::I64 | IdlType::F64 => TypeSize::Fixed(8),
+        IdlType::U128 | IdlType::I128 => TypeSize::Fixed(16),
+        IdlType::PublicKey => TypeSize::Fixed(32),
+        IdlType::Bytes | IdlType::String | IdlType::Vec(_) => TypeSize::Unbounded,
+        IdlType::Defined(inner) => match defs.iter().find(|def| def.name == *inner) {
+            Some(def) => match &def.ty {
+                crate::IdlTypeDefinitionTy::Struct { fields } => get_field_list_size(defs, fields),
+                crate::IdlTypeDefinitionTy::Enum { variants } => {
+                    get_variant_list_size(defs, variants)
+                }
+            },
+            // Types outside of `defs`, such as the ones of a type map, have an unknown size.
+            None => TypeSize::Unbounded,
+        },
+        // `None` is a single byte, `Some` is a byte followed by the value.
+        IdlType::Option(inner) => match get_type_size(defs, inner).max() {
+            Some(size) => TypeSize::Bounded(1 + size),
+            None => TypeSize::Unbounded,
+        },
+        IdlType::Array(inner, len) => match get_type_size(defs, inner) {
+            TypeSize::Fixed(size) => TypeSize::Fixed(size * len),
+            TypeSize::Bounded(size) => TypeSize::Bounded(size * len),
+            TypeSize::Unbounded => TypeSize::Unbounded,
+        },
+    }
+}
+
 /// Generates struct fields from a list of [IdlField]s.
-pub fn generate_fields(fields: &[IdlField]) -> TokenStream {
-    let fields_rendered = fields.iter().map(|arg| {
-        let name = format_ident!("{}", arg.name.to_snake_case());
+pub fn generate_fields(fields: &[IdlField], serde: Option<SerdeOpts>) -> TokenStream {
+    let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+    let fields_rendered = fields.iter().zip(names).map(|(arg, name)| {
+        let docs = generate_docs(arg.docs.as_deref());
+        let alias = name.doc_alias();
+        let serde_attr = generate_serde_field_attr(&arg.ty, serde);
         let type_name = crate::ty_to_rust_type(&arg.ty);
         let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
         quote! {
+            #docs
+            #alias
+            #serde_attr
             pub #name: #stream
         }
     });
@@ -132,14 +277,53 @@ pub fn generate_fields(fields: &[IdlField]) -> TokenStream {
     }
 }
 
+/// Generates the default value of a field, writing out the arrays which don't implement `Default`.
+fn generate_default_value(defs: &[IdlTypeDefinition], ty: &IdlType) -> TokenStream {
+    match ty {
+        IdlType::Array(inner, len) if !get_type_properties(defs, ty).can_derive_default => {
+            let value = generate_default_value(defs, inner);
+            if get_type_properties(defs, inner).can_copy {
+                quote! { [#value; #len] }
+            } else {
+                quote! { core::array::from_fn(|_| #value) }
+            }
+        }
+        _ => quote! { Default::default() },
+    }
+}
+
+/// Generates a `Default` impl for a struct which can't derive it, such as one with an array of
+/// more than 32 items.
+pub fn generate_default_impl(
+    defs: &[IdlTypeDefinition],
+    struct_name: &Ident,
+    fields: &[IdlField],
+) -> TokenStream {
+    let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+    let values = fields
+        .iter()
+        .map(|field| generate_default_value(defs, &field.ty));
+    quote! {
+        impl Default for #struct_name {
+            fn default() -> Self {
+                Self {
+                    #(#names: #values),*
+                }
+            }
+        }
+    }
+}
+
 /// Generates a struct.
 pub fn generate_struct(
     defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
     struct_name: &Ident,
     fields: &[IdlField],
     opts: StructOpts,
+    serde: Option<SerdeOpts>,
 ) -> TokenStream {
-    let fields_rendered = generate_fields(fields);
+    let fields_rendered = generate_fields(fields, serde);
     let props = get_field_list_properties(defs, fields);
 
     let derive_default = if props.can_derive_default {
@@ -149,6 +333,11 @@ pub fn generate_struct(
     } else {
         quote! {}
     };
+    let impl_default = if !props.can_derive_default && props.can_impl_default {
+        generate_default_impl(defs, struct_name, fields)
+    } else {
+        quote! {}
+    };
     let derive_serializers = if opts.zero_copy {
         let repr = if opts.packed {
             quote! {
@@ -177,13 +366,358 @@ pub fn generate_struct(
         }
     };
 
+    // Zero copy structs aren't Borsh-serializable, so only their fixed size is known.
+    let size = if opts.zero_copy && get_field_list_size(defs, fields).fixed().is_none() {
+        quote! {}
+    } else {
+        generate_struct_size(defs, struct_opts, fields, 0, opts)
+    };
+    let derive_serde = generate_serde_derives(serde);
+
     quote! {
         #derive_serializers
         #[derive(Debug)]
         #derive_default
+        #derive_serde
         pub struct #struct_name {
             #fields_rendered
         }
+
+        impl #struct_name {
+            #size
+        }
+
+        #impl_default
+    }
+}
+
+/// Returns true if computing the size of a value of this type can fail, which is when it contains
+/// a type outside of `defs`, such as the ones of a type map, whose size is only known by
+/// serializing it.
+fn size_can_fail(defs: &[IdlTypeDefinition], ty: &IdlType) -> bool {
+    if get_type_size(defs, ty).fixed().is_some() {
+        return false;
+    }
+    match ty {
+        IdlType::Vec(inner) | IdlType::Array(inner, _) | IdlType::Option(inner) => {
+            size_can_fail(defs, inner)
+        }
+        IdlType::Defined(name) => match defs.iter().find(|def| def.name == *name) {
+            Some(def) => match &def.ty {
+                crate::IdlTypeDefinitionTy::Struct { fields } => {
+                    fields.iter().any(|field| size_can_fail(defs, &field.ty))
+                }
+                crate::IdlTypeDefinitionTy::Enum { variants } => {
+                    variant_sizes_can_fail(defs, variants)
+                }
+            },
+            None => true,
+        },
+        _ => false,
+    }
+}
+
+fn variant_sizes_can_fail(defs: &[IdlTypeDefinition], variants: &[IdlEnumVariant]) -> bool {
+    variants.iter().any(|variant| match &variant.fields {
+        Some(EnumFields::Named(fields)) => fields.iter().any(|field| size_can_fail(defs, &field.ty)),
+        Some(EnumFields::Tuple(types)) => types.iter().any(|ty| size_can_fail(defs, ty)),
+        None => false,
+    })
+}
+
+/// Generates an expression computing the Borsh size of `value`, which is of type `ty` or a
+/// reference to it. If [size_can_fail], the expression uses `?` on a `std::io::Result`.
+fn generate_size_expr(defs: &[IdlTypeDefinition], ty: &IdlType, value: TokenStream) -> TokenStream {
+    if let TypeSize::Fixed(size) = get_type_size(defs, ty) {
+        return quote! { #size };
+    }
+    match ty {
+        // Borsh writes the length as a `u32`, followed by the items.
+        IdlType::Bytes | IdlType::String => quote! { 4 + #value.len() },
+        IdlType::Vec(inner) => match get_type_size(defs, inner) {
+            TypeSize::Fixed(size) => quote! { 4 + #value.len() * #size },
+            _ => {
+                let sum = generate_size_sum(defs, inner, quote! { #value.iter() });
+                quote! { 4 + #sum }
+            }
+        },
+        IdlType::Array(inner, _) => generate_size_sum(defs, inner, quote! { #value.iter() }),
+        IdlType::Option(inner) => match get_type_size(defs, inner) {
+            TypeSize::Fixed(1) => quote! { 1 + usize::from(#value.is_some()) },
+            TypeSize::Fixed(size) => quote! { 1 + usize::from(#value.is_some()) * #size },
+            _ if size_can_fail(defs, inner) => {
+                let inner = generate_size_expr(defs, inner, quote! { value });
+                quote! {
+                    1 + #value
+                        .as_ref()
+                        .map_or(Ok(0), |value| -> std::io::Result<usize> { Ok(#inner) })?
+                }
+            }
+            _ => {
+                let inner = generate_size_expr(defs, inner, quote! { value });
+                quote! { 1 + #value.as_ref().map_or(0, |value| #inner) }
+            }
+        },
+        IdlType::Defined(name) if defs.iter().any(|def| def.name == *name) => {
+            if size_can_fail(defs, ty) {
+                quote! { #value.serialized_size()? }
+            } else {
+                quote! { #value.serialized_size() }
+            }
+        }
+        _ => quote! { anchor_lang::AnchorSerialize::try_to_vec(&#value)?.len() },
+    }
+}
+
+/// Generates an expression summing the sizes of the items of type `ty` yielded by `iter`.
+fn generate_size_sum(defs: &[IdlTypeDefinition], ty: &IdlType, iter: TokenStream) -> TokenStream {
+    let item = generate_size_expr(defs, ty, quote! { item });
+    if size_can_fail(defs, ty) {
+        quote! {
+            #iter
+                .map(|item| -> std::io::Result<usize> { Ok(#item) })
+                .sum::<std::io::Result<usize>>()?
+        }
+    } else {
+        quote! { #iter.map(|item| #item).sum::<usize>() }
+    }
+}
+
+/// Generates the signature and body of `serialized_size`, which returns a `std::io::Result` if
+/// any of the sizes [can fail](size_can_fail).
+fn generate_serialized_size_fn(can_fail: bool, doc: &str, body: TokenStream) -> TokenStream {
+    if can_fail {
+        let doc = format!(
+            "{} Fails if a field of a type outside of the IDL can't be serialized.",
+            doc
+        );
+        quote! {
+            #[doc = #doc]
+            pub fn serialized_size(&self) -> std::io::Result<usize> {
+                Ok(#body)
+            }
+        }
+    } else {
+        quote! {
+            #[doc = #doc]
+            pub fn serialized_size(&self) -> usize {
+                #body
+            }
+        }
+    }
+}
+
+/// Generates the `LEN` of a struct with a fixed size, or its `max_size` and `serialized_size`
+/// otherwise. Accounts include their discriminator in the size.
+///
+/// The `LEN` of a `repr(C)` zero copy struct is the one of the struct in memory, padding
+/// included, rather than of its Borsh serialization. It is left out if that depends on the
+/// target, as computed by [c_struct_size].
+pub fn generate_struct_size(
+    defs: &[IdlTypeDefinition],
+    struct_opts: &BTreeMap<String, StructOpts>,
+    fields: &[IdlField],
+    discriminator_len: usize,
+    opts: StructOpts,
+) -> TokenStream {
+    let what = if discriminator_len > 0 {
+        "account data, including the discriminator"
+    } else if opts.zero_copy {
+        "struct in memory"
+    } else {
+        "Borsh serialization"
+    };
+    let size = get_field_list_size(defs, fields);
+    if let TypeSize::Fixed(size) = size {
+        let doc = format!(" Size of the {}.", what);
+        let len = if opts.zero_copy && !opts.packed {
+            match c_struct_size(defs, struct_opts, fields) {
+                Some(size) => discriminator_len + size,
+                None => return quote! {},
+            }
+        } else {
+            discriminator_len + size
+        };
+        return quote! {
+            #[doc = #doc]
+            pub const LEN: usize = #len;
+        };
+    }
+
+    let max_size = size.max().map(|size| {
+        let max_size = discriminator_len + size;
+        let doc = format!(" Size of the largest {}.", what);
+        quote! {
+            #[doc = #doc]
+            pub const fn max_size() -> usize {
+                #max_size
+            }
+        }
+    });
+    let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+    let field_sizes = fields
+        .iter()
+        .zip(names)
+        .map(|(field, name)| generate_size_expr(defs, &field.ty, quote! { self.#name }));
+    let sizes = (discriminator_len > 0)
+        .then(|| quote! { #discriminator_len })
+        .into_iter()
+        .chain(field_sizes);
+    let can_fail = fields.iter().any(|field| size_can_fail(defs, &field.ty));
+    let doc = format!(" Size of the {} of this value.", what);
+    let serialized_size = generate_serialized_size_fn(can_fail, &doc, quote! { #(#sizes)+* });
+    quote! {
+        #max_size
+
+        #serialized_size
+    }
+}
+
+/// Generates the `LEN` of an enum with a fixed size, or its `max_size` and `serialized_size`
+/// otherwise.
+fn generate_enum_size(defs: &[IdlTypeDefinition], variants: &[IdlEnumVariant]) -> TokenStream {
+    let size = get_variant_list_size(defs, variants);
+    if let TypeSize::Fixed(size) = size {
+        return quote! {
+            /// Size of the Borsh serialization.
+            pub const LEN: usize = #size;
+        };
+    }
+
+    let max_size = size.max().map(|size| {
+        quote! {
+            /// Size of the largest Borsh serialization.
+            pub const fn max_size() -> usize {
+                #size
+            }
+        }
+    });
+    let arms = variants.iter().map(|variant| {
+        let variant_name = type_ident(&variant.name);
+        match &variant.fields {
+            // Fields with a fixed size are not bound, since their size doesn't depend on them.
+            Some(EnumFields::Named(fields)) => {
+                let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+                let bindings = fields.iter().zip(&names).map(|(field, name)| {
+                    match get_type_size(defs, &field.ty) {
+                        TypeSize::Fixed(_) => quote! { #name: _ },
+                        _ => quote! { #name },
+                    }
+                });
+                let sizes = fields
+                    .iter()
+                    .zip(&names)
+                    .map(|(field, name)| generate_size_expr(defs, &field.ty, quote! { #name }));
+                quote! {
+                    Self::#variant_name { #(#bindings),* } => 1 #(+ #sizes)*
+                }
+            }
+            Some(EnumFields::Tuple(types)) => {
+                let names: Vec<Ident> = (0..types.len())
+                    .map(|i| format_ident!("field_{}", i))
+                    .collect();
+                let bindings =
+                    types
+                        .iter()
+                        .zip(&names)
+                        .map(|(ty, name)| match get_type_size(defs, ty) {
+                            TypeSize::Fixed(_) => quote! { _ },
+                            _ => quote! { #name },
+                        });
+                let sizes = types
+                    .iter()
+                    .zip(&names)
+                    .map(|(ty, name)| generate_size_expr(defs, ty, quote! { #name }));
+                quote! {
+                    Self::#variant_name(#(#bindings),*) => 1 #(+ #sizes)*
+                }
+            }
+            None => quote! {
+                Self::#variant_name => 1
+            },
+        }
+    });
+    let serialized_size = generate_serialized_size_fn(
+        variant_sizes_can_fail(defs, variants),
+        " Size of the Borsh serialization of this value.",
+        quote! {
+            match self {
+                #(#arms),*
+            }
+        },
+    );
+    quote! {
+        #max_size
+
+        #serialized_size
+    }
+}
+
+/// Generates the fields of a single enum variant.
+pub fn generate_variant_fields(fields: &Option<EnumFields>, serde: Option<SerdeOpts>) -> TokenStream {
+    match fields {
+        Some(EnumFields::Named(fields)) => {
+            let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+            let fields_rendered = fields.iter().zip(names).map(|(field, name)| {
+                let docs = generate_docs(field.docs.as_deref());
+                let alias = name.doc_alias();
+                let serde_attr = generate_serde_field_attr(&field.ty, serde);
+                let type_name = crate::ty_to_rust_type(&field.ty);
+                let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
+                quote! {
+                    #docs
+                    #alias
+                    #serde_attr
+                    #name: #stream
+                }
+            });
+            quote! {
+                { #(#fields_rendered),* }
+            }
+        }
+        Some(EnumFields::Tuple(types)) => {
+            let types_rendered = types.iter().map(|ty| {
+                let serde_attr = generate_serde_field_attr(ty, serde);
+                let type_name = crate::ty_to_rust_type(ty);
+                let stream: proc_macro2::TokenStream = type_name.parse().unwrap();
+                quote! {
+                    #serde_attr
+                    #stream
+                }
+            });
+            quote! {
+                ( #(#types_rendered),* )
+            }
+        }
+        None => quote! {},
+    }
+}
+
+/// Generates the default value of a single enum variant.
+fn generate_variant_default(
+    defs: &[IdlTypeDefinition],
+    fields: &Option<EnumFields>,
+) -> TokenStream {
+    match fields {
+        Some(EnumFields::Named(fields)) => {
+            let names = unique_field_idents(fields.iter().map(|field| field.name.as_str()));
+            let fields_rendered = fields.iter().zip(names).map(|(field, name)| {
+                let value = generate_default_value(defs, &field.ty);
+                quote! {
+                    #name: #value
+                }
+            });
+            quote! {
+                { #(#fields_rendered),* }
+            }
+        }
+        Some(EnumFields::Tuple(types)) => {
+            let types_rendered = types.iter().map(|ty| generate_default_value(defs, ty));
+            quote! {
+                ( #(#types_rendered),* )
+            }
+        }
+        None => quote! {},
     }
 }
 
@@ -192,9 +726,19 @@ pub fn generate_enum(
     defs: &[IdlTypeDefinition],
     enum_name: &Ident,
     variants: &[IdlEnumVariant],
+    serde: Option<SerdeOpts>,
 ) -> TokenStream {
-    let variant_idents = variants.iter().map(|v| format_ident!("{}", v.name));
+    let variant_defs = variants.iter().map(|variant| {
+        let variant_name = type_ident(&variant.name);
+        let alias = variant_name.doc_alias();
+        let fields = generate_variant_fields(&variant.fields, serde);
+        quote! {
+            #alias
+            #variant_name #fields
+        }
+    });
     let props = get_variant_list_properties(defs, variants);
+    let size = generate_enum_size(defs, variants);
 
     let derive_copy = if props.can_copy {
         quote! {
@@ -204,20 +748,38 @@ pub fn generate_enum(
         quote! {}
     };
 
-    let default_variant = format_ident!("{}", variants.first().unwrap().name);
+    // The first variant is the default, as long as all of its fields are.
+    let default_variants = &variants[..variants.len().min(1)];
+    let default_props = get_variant_list_properties(defs, default_variants);
+    let impl_default = match default_variants.first() {
+        Some(default_variant) if default_props.can_impl_default => {
+            let default_name = type_ident(&default_variant.name);
+            let default_fields = generate_variant_default(defs, &default_variant.fields);
+            quote! {
+                impl Default for #enum_name {
+                    fn default() -> Self {
+                        Self::#default_name #default_fields
+                    }
+                }
+            }
+        }
+        _ => quote! {},
+    };
+    let derive_serde = generate_serde_derives(serde);
 
     quote! {
         #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
         #derive_copy
+        #derive_serde
         pub enum #enum_name {
-            #(#variant_idents),*
+            #(#variant_defs),*
         }
 
-        impl Default for #enum_name {
-            fn default() -> Self {
-                Self::#default_variant
-            }
+        impl #enum_name {
+            #size
         }
+
+        #impl_default
     }
 }
 
@@ -225,20 +787,162 @@ pub fn generate_enum(
 pub fn generate_typedefs(
     typedefs: &[IdlTypeDefinition],
     struct_opts: &BTreeMap<String, StructOpts>,
+    serde: Option<SerdeOpts>,
 ) -> TokenStream {
     let defined = typedefs.iter().map(|def| {
-        let struct_name = format_ident!("{}", def.name);
-        match &def.ty {
-            anchor_syn::idl::IdlTypeDefinitionTy::Struct { fields } => {
+        let struct_name = type_ident(&def.name);
+        let docs = generate_docs(def.docs.as_deref());
+        let alias = struct_name.doc_alias();
+        let item = match &def.ty {
+            crate::IdlTypeDefinitionTy::Struct { fields } => {
                 let opts = struct_opts.get(&def.name).copied().unwrap_or_default();
-                generate_struct(typedefs, &struct_name, fields, opts)
+                generate_struct(
+                    typedefs,
+                    struct_opts,
+                    &struct_name.ident,
+                    fields,
+                    opts,
+                    serde,
+                )
             }
-            anchor_syn::idl::IdlTypeDefinitionTy::Enum { variants } => {
-                generate_enum(typedefs, &struct_name, variants)
+            crate::IdlTypeDefinitionTy::Enum { variants } => {
+                generate_enum(typedefs, &struct_name.ident, variants, serde)
             }
+        };
+        quote! {
+            #docs
+            #alias
+            #item
         }
     });
     quote! {
         #(#defined)*
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZERO_COPY: StructOpts = StructOpts {
+        zero_copy: true,
+        packed: false,
+    };
+
+    fn fields(json: &str) -> Vec<IdlField> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn len(fields: &[IdlField], opts: StructOpts) -> Option<String> {
+        let size = generate_struct_size(&[], &BTreeMap::new(), fields, 8, opts).to_string();
+        let (_, len) = size.split_once("pub const LEN : usize = ")?;
+        Some(len.trim_end_matches(" ;").to_string())
+    }
+
+    #[test]
+    fn repr_c_len_includes_padding() {
+        let foo = fields(
+            r#"[{"name": "a", "type": "u8"}, {"name": "b", "type": "u64"},
+                {"name": "c", "type": "publicKey"}]"#,
+        );
+        assert_eq!(len(&foo, ZERO_COPY).as_deref(), Some("56usize"));
+        let packed = StructOpts {
+            packed: true,
+            ..ZERO_COPY
+        };
+        assert_eq!(len(&foo, packed).as_deref(), Some("49usize"));
+        assert_eq!(len(&foo, StructOpts::default()).as_deref(), Some("49usize"));
+    }
+
+    #[test]
+    fn repr_c_len_depending_on_u128_alignment_is_left_out() {
+        // 8 + 32 bytes where `u128` is aligned to 16 bytes, but 8 + 24 on BPF.
+        let padded = fields(r#"[{"name": "a", "type": "u128"}, {"name": "b", "type": "u64"}]"#);
+        assert!(generate_struct_size(&[], &BTreeMap::new(), &padded, 8, ZERO_COPY).is_empty());
+
+        let aligned = fields(
+            r#"[{"name": "a", "type": "u128"}, {"name": "b", "type": "u64"},
+                {"name": "c", "type": "u64"}]"#,
+        );
+        assert_eq!(len(&aligned, ZERO_COPY).as_deref(), Some("40usize"));
+    }
+
+    fn variants(json: &str) -> Vec<IdlEnumVariant> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn enum_variants_have_named_and_tuple_fields() {
+        let variants = variants(
+            r#"[{"name": "Swap", "fields": [{"name": "amountIn", "type": "u64"},
+                                             {"name": "type", "type": "u8"}]},
+                {"name": "Deposit", "fields": ["u64", "publicKey"]},
+                {"name": "Close"}]"#,
+        );
+        let name = type_ident("Action").ident;
+        let generated = generate_enum(&[], &name, &variants, None).to_string();
+        let expected = quote! {
+            pub enum Action {
+                Swap { amount_in: u64, r#type: u8 },
+                Deposit(u64, Pubkey),
+                Close
+            }
+        };
+        assert!(generated.contains(&expected.to_string()));
+        let default = quote! {
+            Self::Swap { amount_in: Default::default(), r#type: Default::default() }
+        };
+        assert!(generated.contains(&default.to_string()));
+    }
+
+    #[test]
+    fn default_is_written_out_for_long_arrays() {
+        let fields = fields(
+            r#"[{"name": "prices", "type": {"array": ["u64", 64]}},
+                {"name": "names", "type": {"array": ["string", 40]}},
+                {"name": "owner", "type": "publicKey"}]"#,
+        );
+        let props = get_field_list_properties(&[], &fields);
+        assert!(!props.can_derive_default);
+        assert!(props.can_impl_default);
+
+        let name = type_ident("Book").ident;
+        let expected = quote! {
+            impl Default for Book {
+                fn default() -> Self {
+                    Self {
+                        prices: [Default::default(); 64usize],
+                        names: core::array::from_fn(|_| Default::default()),
+                        owner: Default::default()
+                    }
+                }
+            }
+        };
+        assert_eq!(
+            generate_default_impl(&[], &name, &fields).to_string(),
+            expected.to_string()
+        );
+    }
+
+    #[test]
+    fn enums_default_to_their_first_variant() {
+        let name = type_ident("Slot").ident;
+        let first = variants(
+            r#"[{"name": "Filled", "fields": [{"array": ["u8", 40]},
+                                               {"option": {"defined": "Unknown"}}]},
+                {"name": "Account", "fields": [{"defined": "Unknown"}]}]"#,
+        );
+        let generated = generate_enum(&[], &name, &first, None).to_string();
+        let default = quote! {
+            Self::Filled([Default::default(); 40usize], Default::default())
+        };
+        assert!(generated.contains(&default.to_string()));
+
+        // Types outside of the IDL's typedefs are not assumed to implement `Default`.
+        let last = variants(
+            r#"[{"name": "Account", "fields": [{"defined": "Unknown"}]}, {"name": "Empty"}]"#,
+        );
+        let generated = generate_enum(&[], &name, &last, None).to_string();
+        assert!(!generated.contains("impl Default"));
+    }
+}
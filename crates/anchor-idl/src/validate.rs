@@ -0,0 +1,114 @@
+//! Checks that an IDL can be generated before any code is generated from it.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{EnumFields, Idl, IdlField, IdlType, IdlTypeDefinition, IdlTypeDefinitionTy};
+
+/// Validates an IDL, returning the JSON path of the first problem along with a description of it.
+pub fn validate_idl(idl: &Idl) -> Result<(), String> {
+    let defined: HashSet<&str> = idl
+        .types
+        .iter()
+        .chain(idl.accounts.iter())
+        .map(|def| def.name.as_str())
+        .collect();
+    let validator = Validator { defined };
+
+    for (i, def) in idl.types.iter().enumerate() {
+        validator.check_type_definition(&format!("types[{}]", i), def)?;
+    }
+    for (i, def) in idl.accounts.iter().enumerate() {
+        let path = format!("accounts[{}]", i);
+        if let IdlTypeDefinitionTy::Enum { .. } = def.ty {
+            return Err(format!(
+                "{}: account `{}` is an enum, which is not supported",
+                path, def.name
+            ));
+        }
+        validator.check_type_definition(&path, def)?;
+    }
+    for (i, ix) in idl.instructions.iter().enumerate() {
+        validator.check_fields(&format!("instructions[{}].args", i), &ix.args)?;
+    }
+    for (i, event) in idl.events.iter().flatten().enumerate() {
+        for (j, field) in event.fields.iter().enumerate() {
+            validator.check_type(&format!("events[{}].fields[{}].type", i, j), &field.ty)?;
+        }
+    }
+    Ok(())
+}
+
+/// Validates that every key of a type map is a typedef of the IDL, and every value a type path.
+pub fn validate_type_map(idl: &Idl, type_map: &BTreeMap<String, String>) -> Result<(), String> {
+    for (name, path) in type_map {
+        if !idl.types.iter().any(|def| def.name == *name) {
+            return Err(format!("type `{}` is not defined by the IDL", name));
+        }
+        if syn::parse_str::<syn::Path>(path).is_err() {
+            return Err(format!("`{}` is not a valid path for type `{}`", path, name));
+        }
+    }
+    Ok(())
+}
+
+struct Validator<'a> {
+    defined: HashSet<&'a str>,
+}
+
+impl Validator<'_> {
+    fn check_type_definition(&self, path: &str, def: &IdlTypeDefinition) -> Result<(), String> {
+        match &def.ty {
+            IdlTypeDefinitionTy::Struct { fields } => {
+                self.check_fields(&format!("{}.type.fields", path), fields)
+            }
+            IdlTypeDefinitionTy::Enum { variants } => {
+                if variants.is_empty() {
+                    return Err(format!(
+                        "{}.type.variants: enum `{}` has no variants",
+                        path, def.name
+                    ));
+                }
+                for (i, variant) in variants.iter().enumerate() {
+                    let path = format!("{}.type.variants[{}]", path, i);
+                    match &variant.fields {
+                        Some(EnumFields::Named(fields)) => {
+                            self.check_fields(&format!("{}.fields", path), fields)?
+                        }
+                        Some(EnumFields::Tuple(types)) => {
+                            for (j, ty) in types.iter().enumerate() {
+                                self.check_type(&format!("{}.fields[{}]", path, j), ty)?;
+                            }
+                        }
+                        None => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn check_fields(&self, path: &str, fields: &[IdlField]) -> Result<(), String> {
+        for (i, field) in fields.iter().enumerate() {
+            self.check_type(&format!("{}[{}].type", path, i), &field.ty)?;
+        }
+        Ok(())
+    }
+
+    fn check_type(&self, path: &str, ty: &IdlType) -> Result<(), String> {
+        match ty {
+            IdlType::Defined(name) => {
+                if !self.defined.contains(name.as_str()) {
+                    return Err(format!(
+                        "{}: type `{}` is not defined by the IDL",
+                        path, name
+                    ));
+                }
+                Ok(())
+            }
+            IdlType::Option(inner) | IdlType::Vec(inner) | IdlType::Array(inner, _) => {
+                self.check_type(path, inner)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+anchor_gen::generate_cpi_crate!("idl.json");
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
@@ -0,0 +1,175 @@
+//! Decodes Drift accounts and instructions laid out as the program writes them: a user with
+//! open orders and positions, and orders placed one at a time or in a batch. The discriminator
+//! dispatch of `InstructionType` is compared against hashing each instruction name in turn.
+
+use anchor_gen::prelude::*;
+use anchor_lang::{prelude::Pubkey, solana_program::hash::hash, AnchorSerialize};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use drift::{
+    args, AccountType, InstructionType, MarketType, Order, OrderParams, OrderStatus,
+    OrderTriggerCondition, OrderType, PerpPosition, PositionDirection, PostOnlyParam, State, User,
+};
+
+/// Instruction names in IDL order, snake cased as Anchor does before hashing them.
+fn instruction_names() -> Vec<String> {
+    let idl: serde_json::Value = serde_json::from_str(include_str!("../idl.json")).unwrap();
+    idl["instructions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|ix| {
+            let mut snake = String::new();
+            for c in ix["name"].as_str().unwrap().chars() {
+                if c.is_ascii_uppercase() {
+                    snake.push('_');
+                }
+                snake.push(c.to_ascii_lowercase());
+            }
+            snake
+        })
+        .collect()
+}
+
+fn instruction_discriminator(name: &str) -> [u8; 8] {
+    hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]
+        .try_into()
+        .unwrap()
+}
+
+/// What dispatch costs without expansion-time discriminators: hash every name until one matches.
+fn hash_scan<'a>(names: &'a [String], data: &[u8]) -> Option<&'a str> {
+    names
+        .iter()
+        .find(|name| data.starts_with(&instruction_discriminator(name)))
+        .map(String::as_str)
+}
+
+fn with_discriminator(discriminator: &[u8], value: &impl AnchorSerialize) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    value.serialize(&mut data).unwrap();
+    data
+}
+
+fn order_params(market_index: u16, price: u64) -> OrderParams {
+    OrderParams {
+        order_type: OrderType::Limit,
+        market_type: MarketType::Perp,
+        direction: PositionDirection::Long,
+        user_order_id: 7,
+        base_asset_amount: 1_000_000_000,
+        price,
+        market_index,
+        reduce_only: false,
+        post_only: PostOnlyParam::MustPostOnly,
+        immediate_or_cancel: false,
+        max_ts: Some(1_700_000_000),
+        trigger_price: None,
+        trigger_condition: OrderTriggerCondition::Above,
+        oracle_price_offset: Some(-25_000),
+        auction_duration: Some(10),
+        auction_start_price: Some(-50_000),
+        auction_end_price: Some(50_000),
+    }
+}
+
+fn user() -> User {
+    let mut user = User {
+        authority: Pubkey::new_unique(),
+        delegate: Pubkey::new_unique(),
+        sub_account_id: 1,
+        next_order_id: 4,
+        open_orders: 3,
+        has_open_order: true,
+        last_active_slot: 250_000_000,
+        ..Default::default()
+    };
+    user.name[..8].copy_from_slice(b"Main Acc");
+    for (i, order) in user.orders.iter_mut().take(3).enumerate() {
+        *order = Order {
+            slot: 250_000_000,
+            price: 100_000_000 + i as u64,
+            base_asset_amount: 1_000_000_000,
+            order_id: i as u32 + 1,
+            market_index: i as u16,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            post_only: true,
+            ..Default::default()
+        };
+    }
+    user.perp_positions[0] = PerpPosition {
+        base_asset_amount: -3_000_000_000,
+        quote_asset_amount: 300_000_000,
+        open_orders: 3,
+        open_asks: -3_000_000_000,
+        ..Default::default()
+    };
+    user
+}
+
+fn decode_accounts(c: &mut Criterion) {
+    let cases = [
+        ("User", with_discriminator(&User::DISCRIMINATOR, &user())),
+        (
+            "State",
+            with_discriminator(&State::DISCRIMINATOR, &State::default()),
+        ),
+    ];
+    for (name, data) in cases {
+        c.bench_function(&format!("AccountType::decode/{}", name), |b| {
+            b.iter(|| AccountType::decode(black_box(&data)).unwrap())
+        });
+    }
+}
+
+fn decode_instructions(c: &mut Criterion) {
+    let place_perp_order = args::PlacePerpOrder {
+        params: order_params(0, 100_000_000),
+    };
+    let place_orders = args::PlaceOrders {
+        params: (0..5)
+            .map(|i| order_params(i, 100_000_000 + u64::from(i)))
+            .collect(),
+    };
+    let cases = [
+        (
+            "placePerpOrder",
+            with_discriminator(&args::PlacePerpOrder::DISCRIMINATOR, &place_perp_order),
+        ),
+        (
+            "placeOrders",
+            with_discriminator(&args::PlaceOrders::DISCRIMINATOR, &place_orders),
+        ),
+    ];
+    for (name, data) in cases {
+        c.bench_function(&format!("InstructionType::decode/{}", name), |b| {
+            b.iter(|| InstructionType::decode(black_box(&data)).unwrap())
+        });
+    }
+}
+
+fn discrim_to_name(c: &mut Criterion) {
+    let names = instruction_names();
+    let cases = [
+        ("first", instruction_discriminator(&names[0])),
+        ("last", instruction_discriminator(names.last().unwrap())),
+    ];
+    for (position, data) in cases {
+        c.bench_function(&format!("match/{}", position), |b| {
+            b.iter(|| InstructionType::discrim_to_name(black_box(&data)).unwrap())
+        });
+        c.bench_function(&format!("hash_scan/{}", position), |b| {
+            b.iter(|| hash_scan(&names, black_box(&data)).unwrap())
+        });
+    }
+}
+
+criterion_group!(
+    benches,
+    decode_accounts,
+    decode_instructions,
+    discrim_to_name
+);
+criterion_main!(benches);
@@ -9,6 +9,7 @@ declare_id!("dRiftyHA39MWEi3m9aunc5MzRF1JYuBsbn6VPcn33UH");
 
 
 #[test]
+#[ignore = "queries mainnet RPC"]
 fn accounts() -> anyhow::Result<()>  {
     let rpc = solana_client::rpc_client::RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
     let key = solana_sdk::pubkey!("H5jfagEnMVNH3PMc2TU2F7tNuXE6b4zCwoL5ip1b4ZHi");
@@ -27,6 +28,7 @@ fn accounts() -> anyhow::Result<()>  {
 }
 
 #[test]
+#[ignore = "queries mainnet RPC"]
 fn instructions() -> anyhow::Result<()>  {
   use std::str::FromStr;
   use solana_transaction_status::UiTransactionEncoding;
@@ -51,25 +53,23 @@ fn instructions() -> anyhow::Result<()>  {
                 let data: Vec<u8> = bs58::decode(ui_decoded_ix.data.clone()).into_vec()?;
                 // only match instruction if it belongs to the IDL that generated this crate (the Drift program)
                 if data.len() >= 8 && ui_decoded_ix.program_id == id().to_string() {
-                  if let Ok(discrim) = data[..8].try_into() {
-                    let ix = InstructionType::decode(&data[..]).map_err(
-                      |e| anyhow::anyhow!("Failed to decode instruction: {:?}", e)
-                    )?;
-                    let name = InstructionType::discrim_to_name(discrim).unwrap();
-                    match ix {
-                      InstructionType::PlacePerpOrder(ix) => {
-                        println!("{}, {:#?}", name, ix._params);
-                      }
-                      InstructionType::PlaceAndTakePerpOrder(ix) => {
-                        println!("{}, {:#?}", name, ix._params);
-                      }
-                      InstructionType::PlaceOrders(ix) => {
-                        for params in ix._params {
-                          println!("{}, {:#?}", name, params);
-                        }
+                  let ix = InstructionType::decode(&data[..]).map_err(
+                    |e| anyhow::anyhow!("Failed to decode instruction: {:?}", e)
+                  )?;
+                  let name = InstructionType::discrim_to_name(&data).unwrap();
+                  match ix {
+                    InstructionType::PlacePerpOrder(ix) => {
+                      println!("{}, {:#?}", name, ix.params);
+                    }
+                    InstructionType::PlaceAndTakePerpOrder(ix) => {
+                      println!("{}, {:#?}", name, ix.params);
+                    }
+                    InstructionType::PlaceOrders(ix) => {
+                      for params in ix.params {
+                        println!("{}, {:#?}", name, params);
                       }
-                      _ => {}
                     }
+                    _ => {}
                   }
                 }
               }
@@ -80,4 +80,34 @@ fn instructions() -> anyhow::Result<()>  {
     }
   }
   Ok(())
-}
\ No newline at end of file
+}
+
+#[test]
+#[ignore = "queries mainnet RPC"]
+fn users_by_authority() -> anyhow::Result<()> {
+  use solana_client::rpc_config::RpcProgramAccountsConfig;
+  use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+
+  fn to_rpc_filter(filter: AccountFilter) -> RpcFilterType {
+    match filter {
+      AccountFilter::Memcmp { offset, bytes } => RpcFilterType::Memcmp(Memcmp::new_raw_bytes(offset, bytes)),
+      AccountFilter::DataSize(len) => RpcFilterType::DataSize(len),
+    }
+  }
+
+  let rpc = solana_client::rpc_client::RpcClient::new("https://api.mainnet-beta.solana.com".to_string());
+  let authority = solana_sdk::pubkey!("H5jfagEnMVNH3PMc2TU2F7tNuXE6b4zCwoL5ip1b4ZHi");
+  let config = RpcProgramAccountsConfig {
+    filters: Some(vec![
+      to_rpc_filter(User::discriminator_filter()),
+      to_rpc_filter(User::filter_authority(&authority)),
+    ]),
+    ..Default::default()
+  };
+  for (key, acct) in rpc.get_program_accounts_with_config(&ID, config)? {
+    if let AccountType::User(user) = AccountType::decode(&acct.data)? {
+      println!("{}: {}", key, user.sub_account_id);
+    }
+  }
+  Ok(())
+}